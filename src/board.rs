@@ -9,7 +9,10 @@
 use anyhow::*;
 
 use super::color::Color::{self, White};
+use super::material::kind_index;
+use super::moves::Move;
 use super::piece::Piece;
+use std::collections::HashMap;
 use std::fmt;
 
 /*********
@@ -18,7 +21,70 @@ use std::fmt;
 
 pub type Tile = usize;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A tile that is known to be on the board, unlike a bare `Tile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// The square of a tile, or `None` if it's not in `0..64`.
+    pub fn new(t: Tile) -> Option<Self> {
+        (t < 64).then_some(Self(t as u8))
+    }
+    pub fn tile(&self) -> Tile {
+        self.0 as Tile
+    }
+    pub fn coord(&self) -> Coord {
+        Coord::from_tile(self.tile())
+    }
+}
+
+impl TryFrom<Coord> for Square {
+    type Error = anyhow::Error;
+
+    fn try_from(c: Coord) -> Result<Self, anyhow::Error> {
+        if c.file() < 8 && c.rank() < 8 {
+            Ok(Self(c.to_usize() as u8))
+        } else {
+            Err(anyhow!(
+                "file {} rank {} is off the board",
+                c.file(),
+                c.rank()
+            ))
+        }
+    }
+}
+
+impl From<Square> for Coord {
+    fn from(s: Square) -> Self {
+        s.coord()
+    }
+}
+
+impl TryFrom<Tile> for Square {
+    type Error = anyhow::Error;
+
+    fn try_from(t: Tile) -> Result<Self, anyhow::Error> {
+        Self::new(t).ok_or_else(|| anyhow!("{} is not a tile of the board", t))
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.coord())
+    }
+}
+
+/// The name of a tile, like `e4`.
+pub fn tile_to_name(t: Tile) -> String {
+    Coord::from_tile(t).to_string()
+}
+
+/// The tile of a square name, like `e4`.
+pub fn name_to_tile(s: &str) -> Result<Tile, anyhow::Error> {
+    s.parse::<Coord>().map(|c| c.to_usize())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Coord {
     file: usize,
     rank: usize,
@@ -33,6 +99,19 @@ impl Coord {
     pub fn new(file: usize, rank: usize) -> Self {
         Self { file, rank }
     }
+    /// The square named by a file letter (`a`-`h`, either case) and a rank digit (`1`-`8`).
+    /// This is the one place square names are decoded, shared by the FEN, SAN and UCI parsers.
+    pub fn from_file_rank_chars(f: char, r: char) -> Option<Self> {
+        let f = f.to_ascii_lowercase();
+        if ('a'..='h').contains(&f) && ('1'..='8').contains(&r) {
+            Some(Self::new(
+                f as usize - 'a' as usize,
+                r as usize - '1' as usize,
+            ))
+        } else {
+            None
+        }
+    }
     pub fn from_tile(t: Tile) -> Self {
         Self {
             file: t % 8,
@@ -42,6 +121,74 @@ impl Coord {
     pub fn to_usize(&self) -> usize {
         self.file + self.rank * 8
     }
+    pub fn file(&self) -> usize {
+        self.file
+    }
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+    /// Returns the square displaced by the given number of files and ranks, if it's on the board.
+    pub fn offset(&self, df: i32, dr: i32) -> Option<Self> {
+        let file = self.file as i32 + df;
+        let rank = self.rank as i32 + dr;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Self::new(file as usize, rank as usize))
+        } else {
+            None
+        }
+    }
+    /// The squares stepped through going `df` files and `dr` ranks at a time, not including this
+    /// one, until leaving the board. A zero step yields nothing.
+    pub fn ray(&self, df: i32, dr: i32) -> impl Iterator<Item = Coord> {
+        let first = if (df, dr) == (0, 0) {
+            None
+        } else {
+            self.offset(df, dr)
+        };
+        std::iter::successors(first, move |c| c.offset(df, dr))
+    }
+    /// For a pawn of `color` that just double-pushed to this square, the square it skipped over.
+    /// `None` if this isn't a square a double push can land on.
+    pub fn en_passant_target(&self, color: Color) -> Option<Coord> {
+        match (color, self.rank) {
+            (White, 3) => Some(Coord::new(self.file, 2)),
+            (Color::Black, 4) => Some(Coord::new(self.file, 5)),
+            _ => None,
+        }
+    }
+    /// All the squares in the rectangle with corners `a` and `b`, both included, in tile order.
+    pub fn rectangle(a: Coord, b: Coord) -> Vec<Coord> {
+        let files = a.file.min(b.file)..=a.file.max(b.file);
+        let ranks = a.rank.min(b.rank)..=a.rank.max(b.rank);
+        ranks
+            .flat_map(|rank| files.clone().map(move |file| Coord::new(file, rank)))
+            .collect()
+    }
+    /// The squares sharing an edge with this one, up to four.
+    pub fn orthogonal_neighbors(&self) -> Vec<Coord> {
+        [
+            self.next_up(),
+            self.next_right(),
+            self.next_down(),
+            self.next_left(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+    /// The squares sharing only a corner with this one, up to four.
+    pub fn diagonal_neighbors(&self) -> Vec<Coord> {
+        [(1, 1), (1, -1), (-1, -1), (-1, 1)]
+            .iter()
+            .filter_map(|(df, dr)| self.offset(*df, *dr))
+            .collect()
+    }
+    /// Every adjacent square, i.e. the squares a king could step to from here.
+    pub fn all_neighbors(&self) -> Vec<Coord> {
+        let mut neighbors = self.orthogonal_neighbors();
+        neighbors.extend(self.diagonal_neighbors());
+        neighbors
+    }
     pub fn next_up(&self) -> Option<Self> {
         if self.rank < 7 {
             Some(Self::new(self.file, self.rank + 1))
@@ -78,11 +225,12 @@ impl FromStr for Coord {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, anyhow::Error> {
-        let file = (s.chars().nth(0).unwrap().to_ascii_lowercase() as u8) - b'a';
-        let rank = (s.chars().nth(1).unwrap().to_ascii_lowercase() as u8) - b'0' - 1;
-
-        let coord = Coord::new(file.into(), rank.into());
-        Ok(coord)
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(f), Some(r), None) => Coord::from_file_rank_chars(f, r),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("invalid square name {}", s))
     }
 }
 
@@ -103,6 +251,87 @@ mod coord_tests {
         for c in valid_coords {
             assert_eq!(c, c.parse::<Coord>().unwrap().to_string())
         }
+        assert_eq!("E4".parse::<Coord>().unwrap(), Coord::new(4, 3));
+        for invalid in ["", "e", "e9", "i1", "4e", "e44"] {
+            assert!(invalid.parse::<Coord>().is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            Coord::from_file_rank_chars('h', '8'),
+            Some(Coord::new(7, 7))
+        );
+        assert_eq!(Coord::from_file_rank_chars('e', '0'), None);
+    }
+
+    #[test]
+    fn test_tile_names() {
+        assert_eq!(tile_to_name(0), "a1");
+        assert_eq!(tile_to_name(28), "e4");
+        assert_eq!(name_to_tile("e4").unwrap(), 28);
+        assert_eq!(name_to_tile("h8").unwrap(), 63);
+        assert!(name_to_tile("i1").is_err());
+        assert!(name_to_tile("e").is_err());
+        let sel = Selection::new(vec![28, 35], SelectionColor::new(255, 0, 0));
+        assert!(sel.to_string().ends_with(": e4 d5"));
+    }
+    #[test]
+    fn test_rectangle() {
+        let block = ["c3", "d3", "e3", "c4", "d4", "e4", "c5", "d5", "e5"];
+        let c3 = "c3".parse::<Coord>().unwrap();
+        let e5 = "e5".parse::<Coord>().unwrap();
+        for rect in [Coord::rectangle(c3, e5), Coord::rectangle(e5, c3)] {
+            let names: Vec<String> = rect.iter().map(|c| c.to_string()).collect();
+            assert_eq!(names, block);
+        }
+        assert_eq!(Coord::rectangle(c3, c3), vec![c3]);
+    }
+
+    #[test]
+    fn test_square() {
+        let e4 = Square::new(28).unwrap();
+        assert_eq!(e4.coord(), "e4".parse().unwrap());
+        assert_eq!(Square::try_from(Coord::new(4, 3)).unwrap(), e4);
+        assert!(Square::try_from(Coord::new(0, 9)).is_err());
+        assert!(Square::try_from(Coord::new(8, 0)).is_err());
+        assert_eq!(e4.to_string(), "e4");
+        assert_eq!(Square::new(64), None);
+        assert!(Square::try_from(99).is_err());
+        let sel = Selection::from_squares(&[e4], SelectionColor::new(0, 0, 255));
+        assert!(sel.contains(e4));
+        assert!(!sel.contains(Square::new(0).unwrap()));
+    }
+
+    #[test]
+    fn test_ray() {
+        let d4: Coord = "d4".parse().unwrap();
+        let diagonal: Vec<String> = d4.ray(1, 1).map(|c| c.to_string()).collect();
+        assert_eq!(diagonal, ["e5", "f6", "g7", "h8"]);
+        assert_eq!(d4.ray(0, -1).count(), 3);
+        assert_eq!(d4.ray(2, 1).last(), Some("h6".parse().unwrap()));
+        assert_eq!(Coord::new(0, 0).ray(-1, 0).next(), None);
+        assert_eq!(d4.ray(0, 0).next(), None);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let corner = Coord::new(0, 0);
+        assert_eq!(corner.orthogonal_neighbors().len(), 2);
+        assert_eq!(corner.all_neighbors().len(), 3);
+        assert_eq!(corner.diagonal_neighbors(), [Coord::new(1, 1)]);
+        let center: Coord = "e4".parse().unwrap();
+        assert_eq!(center.orthogonal_neighbors().len(), 4);
+        assert_eq!(center.all_neighbors().len(), 8);
+        assert!(center.all_neighbors().contains(&"d5".parse().unwrap()));
+        assert_eq!("a4".parse::<Coord>().unwrap().all_neighbors().len(), 5);
+    }
+
+    #[test]
+    fn test_en_passant_target() {
+        let e4 = "e4".parse::<Coord>().unwrap();
+        let d5 = "d5".parse::<Coord>().unwrap();
+        assert_eq!(e4.en_passant_target(White).unwrap().to_string(), "e3");
+        assert_eq!(d5.en_passant_target(Black).unwrap().to_string(), "d6");
+        assert_eq!(e4.en_passant_target(Black), None);
+        assert_eq!(d5.en_passant_target(White), None);
     }
 
     #[test]
@@ -223,7 +452,7 @@ mod coord_tests {
 ** Selections **
 ****************/
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SelectionColor {
     pub red: u8,
     pub green: u8,
@@ -234,6 +463,10 @@ impl SelectionColor {
     pub fn new(red: u8, green: u8, blue: u8) -> Self {
         Self { red, green, blue }
     }
+    /// The same color at half brightness.
+    pub fn darker(&self) -> Self {
+        Self::new(self.red / 2, self.green / 2, self.blue / 2)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -250,7 +483,7 @@ impl fmt::Display for Selection {
             termion::color::Rgb(self.color.red, self.color.green, self.color.blue)
         )?;
         for sq in &self.squares {
-            write!(f, "{}", sq)?;
+            write!(f, " {}", tile_to_name(*sq))?;
         }
         fmt::Result::Ok(())
     }
@@ -260,6 +493,22 @@ impl Selection {
     pub fn new(squares: Vec<Tile>, color: SelectionColor) -> Self {
         Self { squares, color }
     }
+    pub fn from_coords(coords: &[Coord], color: SelectionColor) -> Self {
+        Self::new(coords.iter().map(Coord::to_usize).collect(), color)
+    }
+    /// Same as `new`, but with squares that are sure to be on the board.
+    pub fn from_squares(squares: &[Square], color: SelectionColor) -> Self {
+        Self::new(squares.iter().map(Square::tile).collect(), color)
+    }
+    pub fn contains(&self, s: Square) -> bool {
+        self.squares.contains(&s.tile())
+    }
+    pub fn squares(&self) -> &[Tile] {
+        &self.squares
+    }
+    pub fn color(&self) -> SelectionColor {
+        self.color
+    }
 }
 
 /**********
@@ -321,28 +570,37 @@ impl Board {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Places the pieces of a FEN placement field. The whole field is checked before any piece
+    /// is placed, so the board is left untouched on error.
     pub fn set_position_from_fen(&mut self, piece_placement: &str) -> Result<(), anyhow::Error> {
-        let blocks = piece_placement.split('/');
-
-        if blocks.clone().count() != 8 {
-            return Err(anyhow!(""));
+        let blocks: Vec<&str> = piece_placement.split('/').collect();
+        if blocks.len() != 8 {
+            return Err(anyhow!("placement needs 8 ranks, found {}", blocks.len()));
         }
 
-        for (i, rank_string) in blocks.enumerate() {
+        let mut pieces = vec![];
+        for (i, rank_string) in blocks.iter().enumerate() {
             let rank = 7 - i;
             let mut file = 0;
 
             for c in rank_string.chars() {
-                if c.is_ascii_digit() {
-                    let space = c.to_digit(10).unwrap() as usize;
-                    file += space;
+                if let Some(space) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                    file += space as usize;
                 } else {
-                    // set a piece
-                    let coord = Coord::new(file, rank);
-                    self[coord] = Piece::new_from_fen_char(c);
+                    let piece = Piece::new_from_fen_char(c)
+                        .ok_or_else(|| anyhow!("invalid piece {} on rank {}", c, rank + 1))?;
+                    if file < 8 {
+                        pieces.push((Coord::new(file, rank), piece));
+                    }
                     file += 1;
                 }
             }
+            if file != 8 {
+                return Err(anyhow!("rank {} has {} squares", rank + 1, file));
+            }
+        }
+        for (coord, piece) in pieces {
+            self[coord] = Some(piece);
         }
         Ok(())
     }
@@ -351,12 +609,204 @@ impl Board {
         self.selections.clear();
         self.perspective = White;
     }
+    /// Removes every piece of the given color. Selections are kept.
+    pub fn remove_color(&mut self, c: Color) {
+        self.remove_where(|p| p.get_color() == c);
+    }
+    /// Removes every piece of the same kind as `piece`, of both colors. Selections are kept.
+    pub fn remove_piece_type(&mut self, piece: Piece) {
+        self.remove_where(|p| p.get_letter() == piece.get_letter());
+    }
+    fn remove_where(&mut self, remove: impl Fn(Piece) -> bool) {
+        for square in self.squares.iter_mut() {
+            if square.is_some_and(&remove) {
+                *square = None;
+            }
+        }
+    }
     pub fn add_selection(&mut self, sel: Selection) {
         self.selections.push(sel);
     }
+    /// Marks the tile with the given color, or unmarks it if it already was. Selections of other
+    /// colors are left alone.
+    pub fn toggle_selection(&mut self, t: Tile, color: SelectionColor) {
+        match self.selections.iter_mut().position(|s| s.color == color) {
+            Some(i) => {
+                let squares = &mut self.selections[i].squares;
+                match squares.iter().position(|sq| *sq == t) {
+                    Some(j) => {
+                        squares.remove(j);
+                    }
+                    None => squares.push(t),
+                }
+                if squares.is_empty() {
+                    self.selections.remove(i);
+                }
+            }
+            None => self.add_selection(Selection::new(vec![t], color)),
+        }
+    }
     pub fn clear_selections(&mut self) {
         self.selections.clear();
     }
+    pub fn piece_at_coord(&self, c: Coord) -> Option<Piece> {
+        self[c]
+    }
+    /// The occupied squares only, keyed by coordinate.
+    pub fn as_map(&self) -> HashMap<Coord, Piece> {
+        (0..64)
+            .filter_map(|t| self[t].map(|p| (Coord::from_tile(t), p)))
+            .collect()
+    }
+    /// The pieces in display order, indexed `[row][col]` from the top-left corner as seen from
+    /// `perspective`'s side. From White's side `[0][0]` is a8; from Black's it is h1.
+    pub fn to_grid(&self, perspective: Color) -> [[Option<Piece>; 8]; 8] {
+        let mut grid = [[None; 8]; 8];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                let c = if perspective == White {
+                    Coord::new(col, 7 - row)
+                } else {
+                    Coord::new(7 - col, row)
+                };
+                *cell = self[c];
+            }
+        }
+        grid
+    }
+    /// Returns true if both boards hold the same pieces on the same squares. Selections and
+    /// perspective are only presentation, so they are ignored.
+    pub fn same_position(&self, other: &Board) -> bool {
+        self.squares == other.squares
+    }
+    /// An FNV-1a hash of the placement, for telling cheaply whether any square changed. Unlike
+    /// the Zobrist hash it doesn't cover side to move, castling or en passant.
+    pub fn squares_checksum(&self) -> u64 {
+        self.squares
+            .iter()
+            .fold(0xcbf29ce484222325, |hash, square| {
+                let code = match square {
+                    Some(p) => 1 + kind_index(*p) as u64 + 6 * (p.get_color() != White) as u64,
+                    None => 0,
+                };
+                (hash ^ code).wrapping_mul(0x100000001b3)
+            })
+    }
+    /// Returns a copy of the board with every piece moved to the diagonally-opposite square.
+    /// Unlike changing the perspective, this changes the position itself.
+    pub fn rotated_180(&self) -> Board {
+        self.transformed(|t| 63 - t)
+    }
+    /// Returns a copy of the board mirrored top to bottom, so a1 goes to a8 and e2 to e7.
+    /// Piece colors are kept.
+    pub fn flipped(&self) -> Board {
+        self.transformed(|t| t ^ 56)
+    }
+    /// Moves every piece and selected square from `t` to `map(t)`, so highlights follow the
+    /// pieces.
+    fn transformed(&self, map: fn(Tile) -> Tile) -> Board {
+        let mut board = self.clone();
+        for t in 0..64 {
+            board.squares[map(t)] = self.squares[t];
+        }
+        for sel in &mut board.selections {
+            for t in &mut sel.squares {
+                *t = map(*t);
+            }
+        }
+        board
+    }
+    /// The contents of a rank (0 for the first), from the a file to the h file.
+    pub fn rank(&self, r: usize) -> Result<[Option<Piece>; 8], anyhow::Error> {
+        if r > 7 {
+            return Err(anyhow!("rank {} is off the board", r));
+        }
+        Ok(std::array::from_fn(|f| self[Coord::new(f, r)]))
+    }
+    /// The contents of a file (0 for the a file), from the first rank to the eighth.
+    pub fn file(&self, f: usize) -> Result<[Option<Piece>; 8], anyhow::Error> {
+        if f > 7 {
+            return Err(anyhow!("file {} is off the board", f));
+        }
+        Ok(std::array::from_fn(|r| self[Coord::new(f, r)]))
+    }
+    fn has_pawn_on_file(&self, file: usize, color: Option<Color>) -> bool {
+        (0..8).any(|rank| match self[Coord::new(file, rank)] {
+            Some(Piece::Pawn(c)) => color.is_none_or(|color| c == color),
+            _ => false,
+        })
+    }
+    /// Files (0 for a, 7 for h) without pawns of either color.
+    pub fn open_files(&self) -> Vec<usize> {
+        (0..8)
+            .filter(|f| !self.has_pawn_on_file(*f, None))
+            .collect()
+    }
+    /// Files without pawns of `color` but with some enemy pawn.
+    pub fn semi_open_files(&self, color: Color) -> Vec<usize> {
+        (0..8)
+            .filter(|f| {
+                !self.has_pawn_on_file(*f, Some(color))
+                    && self.has_pawn_on_file(*f, Some(color.opponent()))
+            })
+            .collect()
+    }
+    /// Counts the pawns of `color` on the two ranks in front of the king, on its file and the
+    /// files next to it.
+    pub fn pawn_shield(&self, king: Coord, color: Color) -> u8 {
+        let forward = color.pawn_direction();
+        let mut count = 0;
+        for df in -1..=1 {
+            for steps in 1..=2 {
+                if let Some(sq) = king.offset(df, forward * steps) {
+                    if self[sq] == Some(Piece::Pawn(color)) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+    /// Moves the pieces involved in a move (including the castling rook, the en passant victim
+    /// and the promoted piece) and returns the captured piece, if any. Only the placement
+    /// changes: use `ChessGame::make_move` to also update rights, clocks and side to move.
+    pub fn apply_move(&mut self, m: Move) -> Option<Piece> {
+        if m.castling {
+            self.apply_castling(m);
+            return None;
+        }
+        // in en passant the captured pawn is beside the mover, not on the target
+        let victim_square = if m.enpassant {
+            Coord::new(m.target.file, m.source.rank)
+        } else {
+            m.target
+        };
+        let captured = self[victim_square].take();
+        let piece = self[m.source].take();
+        self[m.target] = m.promoted_piece.or(piece);
+        captured
+    }
+    /// Castling always ends with the king on the g or c file and the rook next to it, wherever
+    /// they started (as in Chess960). The squares between the king and its castling rook must be
+    /// empty, so the rook is the nearest piece on that side of the king.
+    fn apply_castling(&mut self, m: Move) {
+        let rank = m.source.rank;
+        let king_side = m.target.file == 6;
+        let is_rook = |f: &usize| matches!(self[Coord::new(*f, rank)], Some(Piece::Rook(_)));
+        let rook_file = if king_side {
+            (m.source.file + 1..8).find(is_rook)
+        } else {
+            (0..m.source.file).rev().find(is_rook)
+        };
+        let rook_to = Coord::new(if king_side { 5 } else { 3 }, rank);
+        // lift both pieces first, as in Chess960 they may land on each other's square
+        let king = self[m.source].take();
+        let rook = rook_file.and_then(|f| self[Coord::new(f, rank)].take());
+        self[m.target] = king;
+        if rook.is_some() {
+            self[rook_to] = rook;
+        }
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -366,81 +816,582 @@ mod tests {
         let mut b = Board::new();
         b.clear();
         for sq in b.squares {
-            assert!(sq == None);
+            assert!(sq.is_none());
         }
     }
+    #[test]
+    fn test_as_map() {
+        let b = Board::new();
+        let map = b.as_map();
+        assert_eq!(map.len(), 32);
+        assert_eq!(map[&Coord::new(4, 0)], Piece::King(White));
+        assert_eq!(
+            b.piece_at_coord(Coord::new(3, 7)),
+            Some(Piece::Queen(Color::Black))
+        );
+        assert_eq!(b.piece_at_coord(Coord::new(3, 3)), None);
+    }
+    #[test]
+    fn test_apply_move() {
+        let c = |s: &str| s.parse::<Coord>().unwrap();
+        let mut b = Board::new();
+        b.clear();
+        b.set_position_from_fen("r3k3/8/8/3pP3/8/8/8/4K2R").unwrap();
+        let castle = Move::new_castling(c("e1"), c("g1"), c("h1"), White);
+        assert_eq!(b.apply_move(castle), None);
+        assert_eq!(b[c("g1")], Some(Piece::King(White)));
+        assert_eq!(b[c("f1")], Some(Piece::Rook(White)));
+        assert_eq!(b[c("h1")], None);
+
+        let ep = Move::new(c("e5"), c("d6"), Piece::Pawn(White), None)
+            .capture(true)
+            .enpassant(true);
+        assert_eq!(b.apply_move(ep), Some(Piece::Pawn(Color::Black)));
+        assert_eq!(b[c("d5")], None);
+        assert_eq!(b[c("d6")], Some(Piece::Pawn(White)));
+
+        let promotion = Move::new(
+            c("d6"),
+            c("d7"),
+            Piece::Pawn(White),
+            Some(Piece::Queen(White)),
+        );
+        b.apply_move(promotion);
+        assert_eq!(b[c("d7")], Some(Piece::Queen(White)));
+    }
+    #[test]
+    fn test_squares_checksum() {
+        let mut b = Board::new();
+        let start = b.squares_checksum();
+        assert_eq!(start, Board::new().squares_checksum());
+        let (from, to) = (Coord::new(6, 0), Coord::new(5, 2));
+        b[to] = b[from].take();
+        let moved = b.squares_checksum();
+        assert_ne!(moved, start);
+        b[from] = b[to].take();
+        assert_eq!(b.squares_checksum(), start);
+        // swapping colors also changes it
+        b[Coord::new(3, 0)] = Some(Piece::Queen(Color::Black));
+        assert_ne!(b.squares_checksum(), start);
+        // selections don't count
+        b[Coord::new(3, 0)] = Some(Piece::Queen(White));
+        b.toggle_selection(0, SelectionColor::new(255, 0, 0));
+        assert_eq!(b.squares_checksum(), start);
+    }
+    #[test]
+    fn test_to_grid() {
+        let b = Board::new();
+        let white = b.to_grid(White);
+        assert_eq!(white[0][0], Some(Piece::Rook(Color::Black)));
+        assert_eq!(white[0][4], Some(Piece::King(Color::Black)));
+        assert_eq!(white[7][3], Some(Piece::Queen(White)));
+        assert_eq!(white[6][0], Some(Piece::Pawn(White)));
+        let black = b.to_grid(Color::Black);
+        assert_eq!(black[0][0], Some(Piece::Rook(White)));
+        assert_eq!(black[0][3], Some(Piece::King(White)));
+        assert_eq!(black[7][4], Some(Piece::Queen(Color::Black)));
+        assert_eq!(black[3], [None; 8]);
+    }
+    #[test]
+    fn test_rotated_180() {
+        let mut b = Board::new();
+        b[Coord::new(4, 1)] = None;
+        b[Coord::new(4, 3)] = Some(Piece::Pawn(White));
+        let rotated = b.rotated_180();
+        assert_eq!(rotated[Coord::new(3, 4)], Some(Piece::Pawn(White)));
+        assert_eq!(rotated[Coord::new(3, 7)], Some(Piece::King(White)));
+        assert!(!rotated.same_position(&b));
+        assert!(rotated.rotated_180().same_position(&b));
+
+        // the highlight on e4 moves with the pawn to d5
+        let red = SelectionColor::new(255, 0, 0);
+        b.add_selection(Selection::from_coords(&[Coord::new(4, 3)], red));
+        let rotated = b.rotated_180();
+        assert_eq!(
+            rotated.selections[0].squares(),
+            [Coord::new(3, 4).to_usize()]
+        );
+        let flipped = b.flipped();
+        assert_eq!(flipped[Coord::new(4, 4)], Some(Piece::Pawn(White)));
+        assert_eq!(flipped[Coord::new(4, 0)], Some(Piece::King(Color::Black)));
+        assert_eq!(
+            flipped.selections[0].squares(),
+            [Coord::new(4, 4).to_usize()]
+        );
+    }
+    #[test]
+    fn test_rank_and_file() {
+        let b = Board::new();
+        let back_rank = b.rank(0).unwrap();
+        assert_eq!(back_rank[0], Some(Piece::Rook(White)));
+        assert_eq!(back_rank[4], Some(Piece::King(White)));
+        assert_eq!(back_rank[7], Some(Piece::Rook(White)));
+        assert_eq!(b.rank(4).unwrap(), [None; 8]);
+        let e_file = b.file(4).unwrap();
+        assert_eq!(e_file[1], Some(Piece::Pawn(White)));
+        assert_eq!(e_file[7], Some(Piece::King(Color::Black)));
+        assert!(b.rank(8).is_err());
+        assert!(b.file(8).is_err());
+    }
+    #[test]
+    fn test_files_and_pawn_shield() {
+        let mut b = Board::new();
+        b.clear();
+        // white pawns f2 g2 h3, black pawns d5 e6
+        b.set_position_from_fen("6k1/8/4p3/3p4/8/7P/5PP1/6K1")
+            .unwrap();
+        assert_eq!(b.open_files(), [0, 1, 2]);
+        assert_eq!(b.semi_open_files(White), [3, 4]);
+        assert_eq!(b.semi_open_files(Color::Black), [5, 6, 7]);
+        assert_eq!(b.pawn_shield(Coord::new(6, 0), White), 3);
+        assert_eq!(b.pawn_shield(Coord::new(6, 7), Color::Black), 0);
+    }
+    #[test]
+    fn test_bulk_removal() {
+        let mut b = Board::new();
+        b.add_selection(Selection::new(vec![0], SelectionColor::new(255, 0, 0)));
+        b.remove_piece_type(Piece::Queen(White));
+        assert_eq!(b[Coord::new(3, 0)], None);
+        assert_eq!(b[Coord::new(3, 7)], None);
+        assert_eq!(b.as_map().len(), 30);
+        b.remove_color(Color::Black);
+        assert!(b.as_map().values().all(|p| p.get_color() == White));
+        assert_eq!(b.as_map().len(), 15);
+        assert_eq!(b.selections.len(), 1);
+    }
+    #[test]
+    fn test_toggle_selection() {
+        let mut b = Board::new();
+        let red = SelectionColor::new(255, 0, 0);
+        let blue = SelectionColor::new(0, 0, 255);
+        b.toggle_selection(12, red);
+        b.toggle_selection(28, red);
+        b.toggle_selection(12, blue);
+        assert_eq!(b.selections.len(), 2);
+        assert_eq!(b.selections[0].squares, [12, 28]);
+        b.toggle_selection(12, red);
+        assert_eq!(b.selections[0].squares, [28]);
+        assert_eq!(b.selections[1].squares, [12]);
+        b.toggle_selection(12, blue);
+        assert_eq!(b.selections.len(), 1);
+    }
+    #[test]
+    fn test_render_options() {
+        let b = Board::new();
+        let opts = RenderOptions {
+            coordinates: false,
+            cell_width: 1,
+            color: false,
+            color_mode: ColorMode::TrueColor,
+            perspective: White,
+            theme: BoardTheme::default(),
+        };
+        let rendered = b.render(opts);
+        let lines: Vec<&str> = rendered.split("\r\n").collect();
+        assert_eq!(lines[1], "♜♞♝♛♚♝♞♜");
+        assert_eq!(lines[4], "........");
+        assert_eq!(lines[8], "♖♘♗♕♔♗♘♖");
+
+        let opts = RenderOptions {
+            color: false,
+            perspective: Color::Black,
+            ..RenderOptions::default()
+        };
+        let rendered = b.render(opts);
+        let lines: Vec<&str> = rendered.split("\r\n").collect();
+        assert_eq!(lines[1], "    H  G  F  E  D  C  B  A");
+        assert_eq!(lines[2], " 1  ♖  ♘  ♗  ♔  ♕  ♗  ♘  ♖ ");
+    }
+    #[test]
+    fn test_render_both() {
+        let b = Board::new();
+        let opts = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        let (white, black) = b.render_both(opts);
+        assert_eq!(white, b.render(opts));
+        assert_eq!(
+            black,
+            b.render(RenderOptions {
+                perspective: Color::Black,
+                ..opts
+            })
+        );
+        // a rook on a1 is bottom left for White and top right for Black
+        let mut b = Board::new();
+        b.clear();
+        b[Coord::new(0, 0)] = Some(Piece::Rook(White));
+        let (white, black) = b.render_both(opts);
+        let white_lines: Vec<&str> = white.split("\r\n").collect();
+        let black_lines: Vec<&str> = black.split("\r\n").collect();
+        assert_eq!(white_lines[9], " 1  ♖  .  .  .  .  .  .  . ");
+        assert_eq!(black_lines[2], " 1  .  .  .  .  .  .  .  ♖ ");
+    }
+    #[test]
+    fn test_color_downsampling() {
+        use termion::color::Rgb;
+        assert_eq!(ColorMode::to_ansi256(Rgb(255, 0, 0)), 196);
+        assert_eq!(ColorMode::to_ansi256(Rgb(0, 0, 0)), 16);
+        assert_eq!(ColorMode::to_ansi256(Rgb(100, 100, 100)), 241);
+        assert_eq!(ColorMode::to_ansi16(Rgb(200, 200, 200)), 7);
+        assert_eq!(ColorMode::to_ansi16(Rgb(100, 100, 100)), 8);
+        assert_eq!(ColorMode::Ansi16.fg(Rgb(255, 255, 255)), "\x1b[97m");
+        assert_eq!(ColorMode::Ansi16.bg(Rgb(0, 0, 0)), "\x1b[40m");
+        let opts = RenderOptions {
+            color_mode: ColorMode::Ansi256,
+            ..RenderOptions::default()
+        };
+        assert!(!Board::new().render(opts).contains("38;2;"));
+    }
+    #[test]
+    fn test_render_themed() {
+        let b = Board::new();
+        assert_eq!(b.render_themed(&BoardTheme::default()), b.to_string());
+        let dark = b.render_themed(&BoardTheme::dark());
+        assert!(dark.contains(&color::Fg(color::Rgb(230, 120, 40)).to_string()));
+        assert!(dark.contains(&color::Bg(color::Rgb(50, 50, 50)).to_string()));
+        assert!(!dark.contains(&color::Bg(color::Rgb(100, 100, 100)).to_string()));
+    }
     // test fen strings
 }
 
 use termion::color;
-impl fmt::Display for Board {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // print the board from white's perspective
-        let rank_range = if self.perspective == Color::White {
-            (0..8).rev().collect::<Vec<usize>>()
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Show the file letters and rank numbers around the board.
+    pub coordinates: bool,
+    /// Width of each square, in characters. The piece is centered in it.
+    pub cell_width: usize,
+    /// Use terminal colors for tiles, pieces and selections. Without them empty squares are
+    /// drawn as dots.
+    pub color: bool,
+    /// Palette used when `color` is on.
+    pub color_mode: ColorMode,
+    pub perspective: Color,
+    /// Colors of the squares and pieces when `color` is on.
+    pub theme: BoardTheme,
+}
+
+/// The colors of the board diagram.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoardTheme {
+    pub light_square: color::Rgb,
+    pub dark_square: color::Rgb,
+    pub white_pieces: color::Rgb,
+    pub black_pieces: color::Rgb,
+}
+
+impl BoardTheme {
+    /// Gray squares with white and black pieces, the original look.
+    pub fn classic() -> Self {
+        Self {
+            light_square: color::Rgb(200, 200, 200),
+            dark_square: color::Rgb(100, 100, 100),
+            white_pieces: color::Rgb(255, 255, 255),
+            black_pieces: color::Rgb(0, 0, 0),
+        }
+    }
+
+    /// Brown wooden squares.
+    pub fn wood() -> Self {
+        Self {
+            light_square: color::Rgb(240, 217, 181),
+            dark_square: color::Rgb(181, 136, 99),
+            white_pieces: color::Rgb(255, 255, 255),
+            black_pieces: color::Rgb(0, 0, 0),
+        }
+    }
+
+    /// Dim squares for dark terminals, with Black's pieces in orange so they stay visible.
+    pub fn dark() -> Self {
+        Self {
+            light_square: color::Rgb(90, 90, 90),
+            dark_square: color::Rgb(50, 50, 50),
+            white_pieces: color::Rgb(240, 240, 240),
+            black_pieces: color::Rgb(230, 120, 40),
+        }
+    }
+
+    pub fn piece_color(&self, color: Color) -> color::Rgb {
+        if color == White {
+            self.white_pieces
+        } else {
+            self.black_pieces
+        }
+    }
+}
+
+impl Default for BoardTheme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+/// Terminal color support. Colors are defined in RGB and downsampled to the closest color of the
+/// palette when the terminal lacks 24-bit support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+// the standard 16 color palette, as rendered by xterm
+const ANSI16_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+impl ColorMode {
+    /// Guesses the terminal capabilities from the `COLORTERM` and `TERM` environment variables.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorMode::TrueColor
+        } else if term.contains("256color") {
+            ColorMode::Ansi256
         } else {
-            (0..8).collect::<Vec<usize>>()
+            ColorMode::Ansi16
+        }
+    }
+
+    /// Index of the closest color in the 256 color palette.
+    pub fn to_ansi256(rgb: color::Rgb) -> u8 {
+        let color::Rgb(r, g, b) = rgb;
+        if r == g && g == b {
+            // use the finer grayscale ramp (232-255), with the cube corners for black and white
+            return match r {
+                0..=3 => 16,
+                248..=255 => 231,
+                v => 232 + ((v as u16 - 3) * 24 / 245) as u8,
+            };
+        }
+        let level = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+        16 + 36 * level(r) + 6 * level(g) + level(b)
+    }
+
+    /// Index of the closest color in the 16 color palette.
+    pub fn to_ansi16(rgb: color::Rgb) -> u8 {
+        let color::Rgb(r, g, b) = rgb;
+        let distance = |(pr, pg, pb): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, pr) + d(g, pg) + d(b, pb)
         };
-        write!(
-            f,
-            "{}{}\r\n    A  B  C  D  E  F  G  H\r\n",
-            color::Fg(color::White),
-            color::Bg(color::Reset),
-        )?;
-        for rank in rank_range {
-            // at the start of the rank, set the rank name
+        (0..16)
+            .min_by_key(|i| distance(ANSI16_PALETTE[*i]))
+            .unwrap() as u8
+    }
+
+    /// Escape sequence setting the foreground color.
+    pub fn fg(&self, rgb: color::Rgb) -> String {
+        match self {
+            ColorMode::TrueColor => color::Fg(rgb).to_string(),
+            ColorMode::Ansi256 => color::Fg(color::AnsiValue(Self::to_ansi256(rgb))).to_string(),
+            ColorMode::Ansi16 => {
+                let i = Self::to_ansi16(rgb);
+                format!("\x1b[{}m", if i < 8 { 30 + i } else { 82 + i })
+            }
+        }
+    }
+
+    /// Escape sequence setting the background color.
+    pub fn bg(&self, rgb: color::Rgb) -> String {
+        match self {
+            ColorMode::TrueColor => color::Bg(rgb).to_string(),
+            ColorMode::Ansi256 => color::Bg(color::AnsiValue(Self::to_ansi256(rgb))).to_string(),
+            ColorMode::Ansi16 => {
+                let i = Self::to_ansi16(rgb);
+                format!("\x1b[{}m", if i < 8 { 40 + i } else { 92 + i })
+            }
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            coordinates: true,
+            cell_width: 3,
+            color: true,
+            color_mode: ColorMode::TrueColor,
+            perspective: White,
+            theme: BoardTheme::default(),
+        }
+    }
+}
+
+impl Board {
+    fn tile_color(&self, coord: Coord, theme: &BoardTheme) -> color::Rgb {
+        // this sets the tile white or black
+        let base = if (coord.file + coord.rank) & 0x01 == 1 {
+            theme.light_square
+        } else {
+            theme.dark_square
+        };
+        let mut tile_color = base;
+        let blend = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        for sel in &self.selections {
+            if sel.squares.contains(&coord.to_usize()) {
+                tile_color = color::Rgb(
+                    blend(sel.color.red, base.0),
+                    blend(sel.color.green, base.1),
+                    blend(sel.color.blue, base.2),
+                );
+            }
+        }
+        tile_color
+    }
+
+    /// Draws the board diagram with the default options and the given colors.
+    pub fn render_themed(&self, theme: &BoardTheme) -> String {
+        self.render(RenderOptions {
+            perspective: self.perspective,
+            theme: *theme,
+            ..RenderOptions::default()
+        })
+    }
+
+    /// Draws the board diagram with the given options.
+    pub fn render(&self, opts: RenderOptions) -> String {
+        use std::fmt::Write;
+
+        let width = opts.cell_width.max(1);
+        let left_pad = " ".repeat((width - 1) / 2);
+        let right_pad = " ".repeat(width - 1 - (width - 1) / 2);
+        // from Black's side both the ranks and the files are reversed
+        let (rank_range, file_range) = if opts.perspective == Color::White {
+            (
+                (0..8).rev().collect::<Vec<usize>>(),
+                (0..8).collect::<Vec<usize>>(),
+            )
+        } else {
+            ((0..8).collect(), (0..8).rev().collect())
+        };
+        // writing to a String can't fail
+        let mut out = String::new();
+        if opts.color {
             write!(
-                f,
-                "{}{} {} ",
+                out,
+                "{}{}",
                 color::Fg(color::White),
-                color::Bg(color::Reset),
-                rank + 1
-            )?;
-            for file in 0..8 {
+                color::Bg(color::Reset)
+            )
+            .unwrap();
+        }
+        if opts.coordinates {
+            let mut header = String::from("\r\n   ");
+            for file in file_range.iter().copied() {
+                write!(
+                    header,
+                    "{}{}{}",
+                    left_pad,
+                    to_char(file).to_ascii_uppercase(),
+                    right_pad
+                )
+                .unwrap();
+            }
+            out.push_str(header.trim_end());
+        }
+        out.push_str("\r\n");
+        for rank in rank_range {
+            // at the start of the rank, set the rank name
+            if opts.color {
+                write!(
+                    out,
+                    "{}{}",
+                    color::Fg(color::White),
+                    color::Bg(color::Reset)
+                )
+                .unwrap();
+            }
+            if opts.coordinates {
+                write!(out, " {} ", rank + 1).unwrap();
+            }
+            for file in file_range.iter().copied() {
                 let coord = Coord::new(file, rank);
-                let sq = self[coord];
-                let mut tile_color =
-                    // this sets the tile white or black
-                    if (file + rank) & 0x01 == 1 {
-                        color::Rgb(200, 200, 200)
-                    } else {
-                        color::Rgb(100, 100, 100)
-                    };
-
-                for sel in &self.selections {
-                    for selected_square in &sel.squares {
-                        if coord.to_usize() == *selected_square {
-                            if (file + rank) & 0x01 == 1 {
-                                tile_color = color::Rgb(
-                                    ((sel.color.red as u16 + 200) / 2) as u8,
-                                    ((sel.color.green as u16 + 200) / 2) as u8,
-                                    ((sel.color.blue as u16 + 200) / 2) as u8,
-                                );
-                            } else {
-                                tile_color = color::Rgb(
-                                    ((sel.color.red as u16 + 100) / 2) as u8,
-                                    ((sel.color.green as u16 + 100) / 2) as u8,
-                                    ((sel.color.blue as u16 + 100) / 2) as u8,
-                                );
-                            }
+                if opts.color {
+                    out.push_str(&opts.color_mode.bg(self.tile_color(coord, &opts.theme)));
+                    match self[coord] {
+                        Some(piece) => {
+                            let piece_color = opts.theme.piece_color(piece.get_color());
+                            write!(
+                                out,
+                                "{}{}{}{}{}",
+                                left_pad,
+                                opts.color_mode.fg(piece_color),
+                                piece.get_unicode(),
+                                color::Fg(color::Reset),
+                                right_pad
+                            )
                         }
+                        None => write!(out, "{} {}", left_pad, right_pad),
                     }
+                    .unwrap();
+                } else {
+                    let symbol = self[coord].map_or(".", |p| p.get_unicode());
+                    write!(out, "{}{}{}", left_pad, symbol, right_pad).unwrap();
                 }
-
-                match sq {
-                    Some(piece) => write!(f, "{} {} ", color::Bg(tile_color), piece)?,
-                    _ => write!(f, "{}   ", color::Bg(tile_color))?,
-                };
             }
             //end of line
-            write!(f, "{}\r\n", color::Bg(color::Reset))?;
+            if opts.color {
+                write!(out, "{}", color::Bg(color::Reset)).unwrap();
+            }
+            out.push_str("\r\n");
         }
         // add an empty line and clear all styling
-        write!(
-            f,
-            "{}{}\r\n",
-            color::Fg(color::Reset),
-            color::Bg(color::Reset)
+        if opts.color {
+            write!(
+                out,
+                "{}{}",
+                color::Fg(color::Reset),
+                color::Bg(color::Reset)
+            )
+            .unwrap();
+        }
+        out.push_str("\r\n");
+        out
+    }
+}
+
+impl Board {
+    /// The diagram as seen by each player, as (White's view, Black's view).
+    pub fn render_both(&self, opts: RenderOptions) -> (String, String) {
+        (
+            self.render(RenderOptions {
+                perspective: White,
+                ..opts
+            }),
+            self.render(RenderOptions {
+                perspective: Color::Black,
+                ..opts
+            }),
         )
     }
 }
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opts = RenderOptions {
+            perspective: self.perspective,
+            ..RenderOptions::default()
+        };
+        write!(f, "{}", self.render(opts))
+    }
+}
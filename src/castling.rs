@@ -1,11 +1,18 @@
 use std::fmt;
 
+use crate::board::Coord;
+use crate::color::Color::{self, Black, White};
+
 #[derive(Clone, Copy, Debug)]
 pub struct CastlingRights {
     pub white_king_side: bool,
     pub white_queen_side: bool,
     pub black_king_side: bool,
     pub black_queen_side: bool,
+    /// Starting file of the king side rook. Always the h file except in Chess960.
+    pub king_side_file: usize,
+    /// Starting file of the queen side rook. Always the a file except in Chess960.
+    pub queen_side_file: usize,
 }
 
 impl Default for CastlingRights {
@@ -15,6 +22,8 @@ impl Default for CastlingRights {
             white_queen_side: true,
             black_king_side: true,
             black_queen_side: true,
+            king_side_file: 7,
+            queen_side_file: 0,
         }
     }
 }
@@ -22,29 +31,131 @@ impl CastlingRights {
     pub fn new() -> Self {
         Self::default()
     }
+    /// No castling rights at all.
+    pub fn none() -> Self {
+        Self {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+            ..Self::default()
+        }
+    }
+    /// Returns true if the rook files are the ones of standard chess.
+    pub fn is_standard(&self) -> bool {
+        self.king_side_file == 7 && self.queen_side_file == 0
+    }
+    /// The (king side, queen side) rights of a color.
+    pub fn available_for(&self, c: Color) -> (bool, bool) {
+        match c {
+            White => (self.white_king_side, self.white_queen_side),
+            Black => (self.black_king_side, self.black_queen_side),
+        }
+    }
+    /// The side a castling rook starting on `file` castles to: `Some(true)` for the king side,
+    /// `Some(false)` for the queen side and `None` if no castling rook starts there.
+    pub fn side_of_rook_file(&self, file: usize) -> Option<bool> {
+        if file == self.king_side_file {
+            Some(true)
+        } else if file == self.queen_side_file {
+            Some(false)
+        } else {
+            None
+        }
+    }
+    /// Returns true if any side can still castle.
+    pub fn any(&self) -> bool {
+        self.white_king_side
+            || self.white_queen_side
+            || self.black_king_side
+            || self.black_queen_side
+    }
+    /// Removes both castling rights of a color, e.g. after its king moved.
+    pub fn remove_color(&mut self, color: Color) {
+        match color {
+            White => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            Black => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+        }
+    }
+    /// Removes the right tied to a rook's home square, if any. Called for both the source and
+    /// target of every move, so rooks moving away or being captured lose their right.
+    pub fn remove_for_square(&mut self, c: Coord) {
+        let (file, rank) = (c.file(), c.rank());
+        if file == self.queen_side_file && rank == 0 {
+            self.white_queen_side = false;
+        } else if file == self.king_side_file && rank == 0 {
+            self.white_king_side = false;
+        } else if file == self.queen_side_file && rank == 7 {
+            self.black_queen_side = false;
+        } else if file == self.king_side_file && rank == 7 {
+            self.black_king_side = false;
+        }
+    }
 }
 
+// Standard rights are written as KQkq. Chess960 rights are written with the rook files instead
+// (Shredder-FEN), like HAha.
 impl fmt::Display for CastlingRights {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (king_side, queen_side) = if self.is_standard() {
+            ('K', 'Q')
+        } else {
+            (
+                (b'A' + self.king_side_file as u8) as char,
+                (b'A' + self.queen_side_file as u8) as char,
+            )
+        };
         if self.white_king_side {
-            write!(f, "K")?;
+            write!(f, "{}", king_side)?;
         }
         if self.white_queen_side {
-            write!(f, "Q")?;
+            write!(f, "{}", queen_side)?;
         }
         if self.black_king_side {
-            write!(f, "k")?;
+            write!(f, "{}", king_side.to_ascii_lowercase())?;
         }
         if self.black_queen_side {
-            write!(f, "q")?;
+            write!(f, "{}", queen_side.to_ascii_lowercase())?;
         }
-        if !self.white_king_side
-            && !self.white_queen_side
-            && !self.black_king_side
-            && !self.black_queen_side
-        {
+        if !self.any() {
             write!(f, "-")?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::castling::*;
+
+    #[test]
+    fn test_available_for() {
+        let mut rights = CastlingRights::new();
+        assert!(rights.any());
+        rights.remove_for_square("a1".parse().unwrap());
+        assert_eq!(rights.available_for(White), (true, false));
+        assert_eq!(rights.available_for(Black), (true, true));
+        rights.remove_color(White);
+        rights.remove_color(Black);
+        assert!(!rights.any());
+        assert!(!CastlingRights::none().any());
+    }
+
+    #[test]
+    fn test_side_of_rook_file() {
+        let rights = CastlingRights {
+            king_side_file: 6,
+            queen_side_file: 1,
+            ..CastlingRights::new()
+        };
+        assert_eq!(rights.side_of_rook_file(6), Some(true));
+        assert_eq!(rights.side_of_rook_file(1), Some(false));
+        assert_eq!(rights.side_of_rook_file(7), None);
+    }
+}
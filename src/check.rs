@@ -0,0 +1,180 @@
+// Checks and pins of the side to move, found in a single sweep out from the king.
+//
+// With this information most moves can be judged legal without playing them on a copy of the
+// board: a move must capture or block every checker, and a pinned piece must stay on its pin ray.
+// Only king moves and en passant captures still need the full test.
+
+use crate::board::*;
+use crate::movegen::{BISHOP_DIRECTIONS, KNIGHT_OFFSETS, ROOK_DIRECTIONS};
+use crate::moves::Move;
+use crate::piece::Piece::*;
+use crate::ChessGame;
+
+/// A piece that can't leave the line between its king and an enemy slider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pin {
+    pub square: Coord,
+    /// Squares the pinned piece may still move to, from the king (exclusive) to the pinner
+    /// (inclusive), as a bitset of tiles.
+    pub ray: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckInfo {
+    pub checkers: Vec<Coord>,
+    /// Squares that capture or block the check, as a bitset of tiles. All set if not in check.
+    pub check_mask: u64,
+    pub pins: Vec<Pin>,
+}
+
+fn bit(c: Coord) -> u64 {
+    1 << c.to_usize()
+}
+
+impl CheckInfo {
+    pub fn is_in_check(&self) -> bool {
+        !self.checkers.is_empty()
+    }
+
+    /// The ray a piece is pinned to, if it's pinned.
+    pub fn pin_ray(&self, c: Coord) -> Option<u64> {
+        self.pins.iter().find(|p| p.square == c).map(|p| p.ray)
+    }
+
+    /// Returns `Some(legal)` for a pseudo-legal move if the checks and pins decide it, or `None`
+    /// for the king moves and en passant captures that need to be played out.
+    pub fn allows(&self, m: &Move) -> Option<bool> {
+        if matches!(m.piece, King(_)) || m.enpassant {
+            return None;
+        }
+        if self.checkers.len() > 1 {
+            return Some(false);
+        }
+        let target = bit(m.target);
+        Some(
+            self.check_mask & target != 0
+                && self.pin_ray(m.source).is_none_or(|ray| ray & target != 0),
+        )
+    }
+}
+
+impl ChessGame {
+    /// Finds every piece checking the side to move's king and every piece pinned to it.
+    pub fn check_info(&self) -> CheckInfo {
+        let mut info = CheckInfo {
+            checkers: vec![],
+            check_mask: u64::MAX,
+            pins: vec![],
+        };
+        let color = self.side_to_move;
+        let king = match self.find_king(color) {
+            Some(k) => k,
+            None => return info,
+        };
+        let enemy = color.opponent();
+        let mut mask = 0;
+
+        // pawns attack diagonally forward, so look forward from the king
+        let pawn_dr = color.pawn_direction();
+        let jumpers = [-1, 1]
+            .iter()
+            .filter_map(|df| king.offset(*df, pawn_dr).map(|sq| (sq, Pawn(enemy))))
+            .chain(
+                KNIGHT_OFFSETS
+                    .iter()
+                    .filter_map(|(df, dr)| king.offset(*df, *dr).map(|sq| (sq, Knight(enemy)))),
+            );
+        for (sq, attacker) in jumpers {
+            if self.board[sq] == Some(attacker) {
+                info.checkers.push(sq);
+                mask |= bit(sq);
+            }
+        }
+
+        for (directions, slider) in [
+            (ROOK_DIRECTIONS, Rook(enemy)),
+            (BISHOP_DIRECTIONS, Bishop(enemy)),
+        ] {
+            for (df, dr) in directions {
+                let mut ray = 0;
+                let mut blocker = None;
+                let mut sq = king;
+                while let Some(next) = sq.offset(df, dr) {
+                    sq = next;
+                    ray |= bit(sq);
+                    match self.board[sq] {
+                        None => continue,
+                        Some(p) if p == slider || p == Queen(enemy) => {
+                            match blocker {
+                                None => {
+                                    info.checkers.push(sq);
+                                    mask |= ray;
+                                }
+                                Some(square) => info.pins.push(Pin { square, ray }),
+                            }
+                            break;
+                        }
+                        Some(p) if p.get_color() == color && blocker.is_none() => {
+                            blocker = Some(sq)
+                        }
+                        Some(_) => break,
+                    }
+                }
+            }
+        }
+
+        if info.is_in_check() {
+            info.check_mask = mask;
+        }
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn c(name: &str) -> Coord {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn test_check_info() {
+        let game = ChessGame::new();
+        let info = game.check_info();
+        assert!(!info.is_in_check());
+        assert!(info.pins.is_empty());
+
+        // the knight on d2 is pinned by the bishop on b4, and the queen on e7 gives check
+        let game = ChessGame::new_position("4k3/4q3/8/8/1b6/8/3N4/4K3 w - - 0 1").unwrap();
+        let info = game.check_info();
+        assert_eq!(info.checkers, [c("e7")]);
+        assert!(info.check_mask & (1 << c("e4").to_usize()) != 0);
+        assert!(info.check_mask & (1 << c("d4").to_usize()) == 0);
+        assert_eq!(info.pins.len(), 1);
+        assert_eq!(info.pins[0].square, c("d2"));
+        assert!(info.pin_ray(c("d2")).unwrap() & (1 << c("b4").to_usize()) != 0);
+
+        // double check from a knight and a rook
+        let game = ChessGame::new_position("4k3/8/8/8/8/3n4/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(game.check_info().checkers.len(), 2);
+    }
+
+    #[test]
+    fn test_check_info_matches_clone_test() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            let info = game.check_info();
+            for m in game.pseudo_legal_moves() {
+                if let Some(legal) = info.allows(&m) {
+                    assert_eq!(legal, game.is_king_safe_after(&m), "{} {}", fen, m.to_uci());
+                }
+            }
+        }
+    }
+}
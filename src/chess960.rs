@@ -0,0 +1,136 @@
+// Chess960 (Fischer random chess) starting positions.
+
+use anyhow::*;
+
+use crate::board::Coord;
+use crate::castling::CastlingRights;
+use crate::color::Color::{Black, White};
+use crate::piece::Piece::{self, *};
+use crate::ChessGame;
+
+/// Knight placements over the five squares left after placing bishops and queen, indexed by the
+/// remainder of the Scharnagl numbering.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The white back rank of a Chess960 starting position, from the a file to the h file.
+pub fn chess960_back_rank(position_number: u16) -> Result<[Piece; 8], anyhow::Error> {
+    if position_number >= 960 {
+        return Err(anyhow!(
+            "Chess960 positions go from 0 to 959, got {}",
+            position_number
+        ));
+    }
+    let mut rank: [Option<Piece>; 8] = [None; 8];
+    let place_on_empty = |rank: &mut [Option<Piece>; 8], index: usize, piece: Piece| {
+        let file = (0..8).filter(|f| rank[*f].is_none()).nth(index).unwrap();
+        rank[file] = Some(piece);
+    };
+    let mut n = position_number as usize;
+    // light squared bishop on b, d, f or h, then dark squared bishop on a, c, e or g
+    rank[2 * (n % 4) + 1] = Some(Bishop(White));
+    n /= 4;
+    rank[2 * (n % 4)] = Some(Bishop(White));
+    n /= 4;
+    place_on_empty(&mut rank, n % 6, Queen(White));
+    n /= 6;
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    // placing the first knight would shift the empty squares after it
+    place_on_empty(&mut rank, second, Knight(White));
+    place_on_empty(&mut rank, first, Knight(White));
+    // the king always goes between the rooks
+    for piece in [Rook(White), King(White), Rook(White)] {
+        place_on_empty(&mut rank, 0, piece);
+    }
+    Ok(rank.map(|p| p.unwrap()))
+}
+
+impl ChessGame {
+    /// Sets up the Chess960 starting position with the given Scharnagl number (0 to 959), with
+    /// full castling rights on the files of its rooks. Position 518 is the standard start.
+    pub fn new_chess960(position_number: u16) -> Result<ChessGame, anyhow::Error> {
+        let back_rank = chess960_back_rank(position_number)?;
+        let mut game = ChessGame::new();
+        game.board.clear();
+        let mut rook_files = vec![];
+        for (file, piece) in back_rank.iter().enumerate() {
+            game.board[Coord::new(file, 0)] = Some(*piece);
+            game.board[Coord::new(file, 1)] = Some(Pawn(White));
+            game.board[Coord::new(file, 6)] = Some(Pawn(Black));
+            game.board[Coord::new(file, 7)] = Some(piece.with_color(Black));
+            if *piece == Rook(White) {
+                rook_files.push(file);
+            }
+        }
+        game.castling_rights = CastlingRights {
+            queen_side_file: rook_files[0],
+            king_side_file: rook_files[1],
+            ..CastlingRights::new()
+        };
+        game.reset_repetitions();
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_new_chess960() {
+        let classic = ChessGame::new_chess960(518).unwrap();
+        assert_eq!(classic.to_fen(), ChessGame::new().to_fen());
+
+        let first = ChessGame::new_chess960(0).unwrap();
+        assert_eq!(
+            first.to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+        let last = ChessGame::new_chess960(959).unwrap();
+        assert_eq!(
+            last.board.to_fen_placement(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB"
+        );
+        assert!(ChessGame::new_chess960(960).is_err());
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let mut game = ChessGame::new_position(fen).unwrap();
+        assert_eq!(game.perft(3), 12189);
+        // the king stays on g1 and the rook jumps from h1 to f1... once f1 is free
+        assert!(game.castling_move(White, true).is_none());
+        game.apply_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BN1KR w HFhf - 2 9")
+            .unwrap();
+        let castle = game.castling_move(White, true).unwrap();
+        assert_eq!(game.move_to_san(castle), "O-O");
+        game.make_move(castle).unwrap();
+        assert_eq!(
+            game.board.to_fen_placement(),
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRK1"
+        );
+        assert_eq!(game.castling_rights.to_string(), "hf");
+        game.unmake_move();
+        assert_eq!(
+            game.to_fen(),
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BN1KR w HFhf - 2 9"
+        );
+    }
+
+    #[test]
+    fn test_chess960_fen_roundtrip() {
+        let fen = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1";
+        assert_eq!(ChessGame::new_position(fen).unwrap().to_fen(), fen);
+    }
+}
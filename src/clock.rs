@@ -0,0 +1,208 @@
+// Time controls and chess clocks.
+//
+// A `TimeControl` is read from the PGN `TimeControl` tag, and a `ClockState` keeps the time left
+// for each side. Games don't need a clock: `ChessGame::clock` is `None` unless one is set.
+
+use anyhow::*;
+
+use crate::color::Color::{self, White};
+use crate::moves::Move;
+use crate::ChessGame;
+
+/// One period of a time control: `moves` moves (or the rest of the game if `None`) to be played
+/// in `base_ms`, with `increment_ms` added after every move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimePeriod {
+    pub moves: Option<u32>,
+    pub base_ms: u64,
+    pub increment_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub periods: Vec<TimePeriod>,
+}
+
+fn parse_seconds(s: &str) -> Result<u64, anyhow::Error> {
+    s.parse::<u64>()
+        .ok()
+        .and_then(|secs| secs.checked_mul(1000))
+        .ok_or_else(|| anyhow!("invalid number of seconds {}", s))
+}
+
+impl TimeControl {
+    /// Reads the value of a PGN `TimeControl` tag, like `40/7200:3600`, `300+2` or `*180`. The
+    /// unknown (`?`) and untimed (`-`) values give `None`. Sandclock periods are read as a base
+    /// time without increment.
+    pub fn from_pgn_tag(tag: &str) -> Result<Option<TimeControl>, anyhow::Error> {
+        let tag = tag.trim();
+        if tag == "?" || tag == "-" {
+            return Ok(None);
+        }
+        let mut periods = vec![];
+        for field in tag.split(':') {
+            let field = field.strip_prefix('*').unwrap_or(field);
+            let (moves, time) = match field.split_once('/') {
+                Some((moves, time)) => (
+                    Some(
+                        moves
+                            .parse::<u32>()
+                            .map_err(|_| anyhow!("invalid number of moves {}", moves))?,
+                    ),
+                    time,
+                ),
+                None => (None, field),
+            };
+            let (base, increment) = time.split_once('+').unwrap_or((time, "0"));
+            periods.push(TimePeriod {
+                moves,
+                base_ms: parse_seconds(base)?,
+                increment_ms: parse_seconds(increment)?,
+            });
+        }
+        Ok(Some(TimeControl { periods }))
+    }
+}
+
+/// The time left on each side's clock, in milliseconds. It goes negative when a side runs out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockState {
+    pub white_ms: i64,
+    pub black_ms: i64,
+    pub white_increment_ms: i64,
+    pub black_increment_ms: i64,
+}
+
+impl ClockState {
+    pub fn new(base_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            white_ms: base_ms as i64,
+            black_ms: base_ms as i64,
+            white_increment_ms: increment_ms as i64,
+            black_increment_ms: increment_ms as i64,
+        }
+    }
+
+    /// A clock set to the first period of a time control.
+    pub fn from_time_control(tc: &TimeControl) -> Option<Self> {
+        tc.periods
+            .first()
+            .map(|p| Self::new(p.base_ms, p.increment_ms))
+    }
+
+    /// Reads the clocks from a UCI `go` command, like `go wtime 60000 btime 58000 winc 1000
+    /// binc 1000`. Missing values are taken as zero.
+    pub fn from_uci_go(line: &str) -> Result<Self, anyhow::Error> {
+        let mut clock = Self::new(0, 0);
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let field = match token {
+                "wtime" => &mut clock.white_ms,
+                "btime" => &mut clock.black_ms,
+                "winc" => &mut clock.white_increment_ms,
+                "binc" => &mut clock.black_increment_ms,
+                _ => continue,
+            };
+            let value = tokens
+                .next()
+                .ok_or_else(|| anyhow!("missing value for {}", token))?;
+            *field = value
+                .parse()
+                .map_err(|_| anyhow!("invalid value {} for {}", value, token))?;
+        }
+        Ok(clock)
+    }
+
+    pub fn remaining_ms(&self, color: Color) -> i64 {
+        if color == White {
+            self.white_ms
+        } else {
+            self.black_ms
+        }
+    }
+
+    /// Returns true if `color` has run out of time.
+    pub fn flagged(&self, color: Color) -> bool {
+        self.remaining_ms(color) <= 0
+    }
+
+    /// Takes the time `color` spent on a move off its clock, then adds its increment unless the
+    /// flag fell during the move: a side that overstepped its time stays flagged.
+    pub fn spend(&mut self, color: Color, elapsed_ms: u64) {
+        let (time, increment) = if color == White {
+            (&mut self.white_ms, self.white_increment_ms)
+        } else {
+            (&mut self.black_ms, self.black_increment_ms)
+        };
+        *time -= elapsed_ms as i64;
+        if *time > 0 {
+            *time += increment;
+        }
+    }
+}
+
+impl ChessGame {
+    /// Plays a move and charges the time spent on it to the mover's clock, if the game has one.
+    /// Undoing the move doesn't give the time back.
+    pub fn make_timed_move(&mut self, m: Move, elapsed_ms: u64) -> Result<(), anyhow::Error> {
+        let mover = self.side_to_move;
+        self.make_move(m)?;
+        if let Some(clock) = &mut self.clock {
+            clock.spend(mover, elapsed_ms);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::*;
+    use crate::color::Color::Black;
+
+    #[test]
+    fn test_time_control_tag() {
+        let tc = TimeControl::from_pgn_tag("40/7200:3600").unwrap().unwrap();
+        assert_eq!(tc.periods.len(), 2);
+        assert_eq!(tc.periods[0].moves, Some(40));
+        assert_eq!(tc.periods[0].base_ms, 7_200_000);
+        assert_eq!(tc.periods[1].moves, None);
+        let blitz = TimeControl::from_pgn_tag("300+2").unwrap().unwrap();
+        assert_eq!(blitz.periods[0].increment_ms, 2000);
+        assert_eq!(TimeControl::from_pgn_tag("-").unwrap(), None);
+        assert!(TimeControl::from_pgn_tag("5 min").is_err());
+    }
+
+    #[test]
+    fn test_clock_state() {
+        let mut game = ChessGame::new();
+        assert!(game.clock.is_none());
+        let tc = TimeControl::from_pgn_tag("60+1").unwrap().unwrap();
+        game.clock = ClockState::from_time_control(&tc);
+        game.make_timed_move(game.parse_san("e4").unwrap(), 5000)
+            .unwrap();
+        game.make_timed_move(game.parse_san("e5").unwrap(), 62000)
+            .unwrap();
+        let clock = game.clock.unwrap();
+        assert_eq!(clock.remaining_ms(White), 56000);
+        assert_eq!(clock.remaining_ms(Black), -2000);
+        assert!(clock.flagged(Black));
+
+        let uci =
+            ClockState::from_uci_go("go wtime 60000 btime 58000 winc 1000 binc 1000").unwrap();
+        assert_eq!(uci.black_ms, 58000);
+        assert_eq!(uci.white_increment_ms, 1000);
+        assert!(ClockState::from_uci_go("go wtime").is_err());
+    }
+
+    #[test]
+    fn test_overstep_keeps_the_flag() {
+        let mut clock = ClockState::new(60000, 1000);
+        clock.spend(White, 60500);
+        assert_eq!(clock.remaining_ms(White), -500);
+        assert!(clock.flagged(White));
+        // running out exactly also flags
+        clock.spend(Black, 60000);
+        assert!(clock.flagged(Black));
+        assert!(TimeControl::from_pgn_tag("18446744073709551615").is_err());
+    }
+}
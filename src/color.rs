@@ -27,6 +27,42 @@ impl Color {
             White
         }
     }
+    /// The name of the color, capitalized: "White" or "Black".
+    pub fn name(self) -> &'static str {
+        if self == White {
+            "White"
+        } else {
+            "Black"
+        }
+    }
+    /// The rank direction pawns of this color move in: +1 for White, -1 for Black.
+    pub fn pawn_direction(self) -> i32 {
+        if self == White {
+            1
+        } else {
+            -1
+        }
+    }
+    /// The rank where this color's pieces start (0 for White, 7 for Black).
+    pub fn back_rank(self) -> usize {
+        if self == White {
+            0
+        } else {
+            7
+        }
+    }
+    /// The rank where this color's pawns promote, which is the opponent's back rank.
+    pub fn promotion_rank(self) -> usize {
+        self.opponent().back_rank()
+    }
+    /// The rank where this color's pawns start and can double push from.
+    pub fn pawn_start_rank(self) -> usize {
+        if self == White {
+            1
+        } else {
+            6
+        }
+    }
 }
 
 use std::ops::Index;
@@ -45,3 +81,18 @@ impl<T> IndexMut<Color> for [T] {
         &mut self[color as usize]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color::*;
+
+    #[test]
+    fn test_rank_helpers() {
+        assert_eq!(White.pawn_direction(), 1);
+        assert_eq!(Black.pawn_direction(), -1);
+        assert_eq!((White.back_rank(), Black.back_rank()), (0, 7));
+        assert_eq!((White.promotion_rank(), Black.promotion_rank()), (7, 0));
+        assert_eq!((White.pawn_start_rank(), Black.pawn_start_rank()), (1, 6));
+        assert_eq!((White.name(), Black.name()), ("White", "Black"));
+    }
+}
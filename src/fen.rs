@@ -0,0 +1,414 @@
+// FEN export and utilities. Parsing lives in ChessGame::apply_fen.
+//
+// EPD is FEN without the clocks, followed by `opcode operand;` operations. The `hmvc` and
+// `fmvn` operations stand in for the clocks.
+
+use anyhow::*;
+
+use crate::board::{Board, Coord};
+use crate::color::Color::{self, Black, White};
+use crate::piece::Piece::*;
+use crate::ChessGame;
+
+impl Board {
+    /// The piece placement field of a FEN string, rank 8 first.
+    pub fn to_fen_placement(&self) -> String {
+        self.fen_ranks().collect::<Vec<String>>().join("/")
+    }
+
+    /// The eight ranks of the FEN placement field, rank 8 first.
+    pub fn fen_ranks(&self) -> impl Iterator<Item = String> + '_ {
+        (0..8).rev().map(move |rank| self.fen_rank(rank))
+    }
+
+    fn fen_rank(&self, rank: usize) -> String {
+        let mut row = String::new();
+        let mut empty = 0;
+        for file in 0..8 {
+            match self[Coord::new(file, rank)] {
+                Some(piece) => {
+                    if empty > 0 {
+                        row.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    let letter = piece.get_letter();
+                    if piece.get_color() == White {
+                        row.push_str(letter);
+                    } else {
+                        row.push_str(&letter.to_ascii_lowercase());
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            row.push_str(&empty.to_string());
+        }
+        row
+    }
+}
+
+impl ChessGame {
+    /// Exports the game as a FEN string.
+    pub fn to_fen(&self) -> String {
+        let side = if self.side_to_move == White { "w" } else { "b" };
+        let en_passant = match self.enpassant_target_square {
+            Some(c) => c.to_string(),
+            None => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            self.board.to_fen_placement(),
+            side,
+            self.castling_rights,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_clock
+        )
+    }
+
+    /// The FEN of every position of the game, from the one it started from to the current one:
+    /// one more entry than there are moves in the history.
+    pub fn fen_history(&self) -> Vec<String> {
+        let mut game = self.clone();
+        let mut fens = vec![game.to_fen()];
+        while game.unmake_move().is_some() {
+            fens.push(game.to_fen());
+        }
+        fens.reverse();
+        fens
+    }
+
+    /// Sets up a game from just a piece placement and the side to move, as puzzles are often
+    /// given. There are no castling rights or en passant square, and the clocks are 0 and 1.
+    pub fn from_placement(placement: &str, side: Color) -> Result<ChessGame, anyhow::Error> {
+        if placement.split_whitespace().count() != 1 {
+            return Err(anyhow!(
+                "expected a bare piece placement, got {}",
+                placement
+            ));
+        }
+        let side = if side == White { "w" } else { "b" };
+        ChessGame::new_position(&format!("{} {} - - 0 1", placement, side))
+    }
+
+    /// Sets up a game from an EPD line. The clocks are read from the `hmvc` and `fmvn`
+    /// operations when present, and default to 0 and 1 otherwise. Other operations are ignored.
+    pub fn from_epd(epd: &str) -> Result<ChessGame, anyhow::Error> {
+        let fields: Vec<&str> = epd.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(anyhow!("EPD needs 4 fields, found {}", fields.len()));
+        }
+        let (mut halfmove, mut fullmove) = ("0", "1");
+        let operations = fields[4..].join(" ");
+        for operation in operations.split(';') {
+            let mut parts = operation.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("hmvc"), Some(n)) => halfmove = n,
+                (Some("fmvn"), Some(n)) => fullmove = n,
+                _ => (),
+            }
+        }
+        ChessGame::new_position(&format!(
+            "{} {} {}",
+            fields[..4].join(" "),
+            halfmove,
+            fullmove
+        ))
+    }
+}
+
+/// Splits a FEN string into its six fields.
+pub(crate) fn fen_fields(fen: &str) -> Result<Vec<&str>, anyhow::Error> {
+    let fields: Vec<&str> = fen.split_ascii_whitespace().collect();
+    if fields.len() != 6 {
+        return Err(anyhow!("FEN needs 6 fields, found {}", fields.len()));
+    }
+    Ok(fields)
+}
+
+/// The piece placement field of a FEN string, without parsing it.
+pub fn fen_placement(fen: &str) -> Result<&str, anyhow::Error> {
+    Ok(fen_fields(fen)?[0])
+}
+
+/// The side to move of a FEN string, without parsing the rest of it.
+pub fn fen_side_to_move(fen: &str) -> Result<Color, anyhow::Error> {
+    match fen_fields(fen)?[1] {
+        "w" | "W" => Ok(White),
+        "b" | "B" => Ok(Black),
+        other => Err(anyhow!("invalid side to move {}", other)),
+    }
+}
+
+/// Checks that a FEN is well formed and describes a sane position, without building a game:
+/// six fields, eight ranks of eight squares, one king per side, no pawns on the first or last
+/// rank, castling rights backed by a king and rook on the back rank, an en passant square on the
+/// right rank for the side to move, and numeric clocks. Returns the first problem found.
+///
+/// The placement is read by the same parser as `ChessGame::apply_fen`. The clocks are checked
+/// more strictly on purpose: `apply_fen` falls back to defaults for clocks that aren't numbers,
+/// so that database FENs still load, while this reports them.
+pub fn validate_fen(fen: &str) -> Result<(), anyhow::Error> {
+    let fields = fen_fields(fen)?;
+
+    let mut board = Board::new();
+    board.clear();
+    board.set_position_from_fen(fields[0])?;
+    let at = |file: usize, rank: usize| board[Coord::new(file, rank)];
+
+    for color in [White, Black] {
+        let kings = (0..64).filter(|t| board[*t] == Some(King(color))).count();
+        if kings != 1 {
+            return Err(anyhow!("{:?} has {} kings", color, kings));
+        }
+    }
+    for rank in [0, 7] {
+        if (0..8).any(|f| matches!(at(f, rank), Some(Pawn(_)))) {
+            return Err(anyhow!("pawn on rank {}", rank + 1));
+        }
+    }
+
+    let side = fen_side_to_move(fen)?;
+
+    if fields[2] != "-" {
+        for c in fields[2].chars() {
+            let color = if c.is_ascii_uppercase() { White } else { Black };
+            let rank = color.back_rank();
+            let king_file = (0..8)
+                .find(|f| at(*f, rank) == Some(King(color)))
+                .ok_or_else(|| anyhow!("castling right {} without the king at home", c))?;
+            let is_rook = |f: usize| at(f, rank) == Some(Rook(color));
+            let backed = match c.to_ascii_lowercase() {
+                'k' => (king_file + 1..8).any(is_rook),
+                'q' => (0..king_file).any(is_rook),
+                f @ 'a'..='h' => is_rook(f as usize - 'a' as usize),
+                _ => return Err(anyhow!("invalid castling right {}", c)),
+            };
+            if !backed {
+                return Err(anyhow!("castling right {} without its rook", c));
+            }
+        }
+    }
+
+    if fields[3] != "-" {
+        let expected_rank = if side == White { '6' } else { '3' };
+        match fields[3].chars().collect::<Vec<char>>()[..] {
+            ['a'..='h', r] if r == expected_rank => (),
+            _ => return Err(anyhow!("invalid en passant square {}", fields[3])),
+        }
+    }
+
+    for clock in &fields[4..] {
+        clock
+            .parse::<u32>()
+            .map_err(|_| anyhow!("invalid clock {}", clock))?;
+    }
+    Ok(())
+}
+
+/// Parses and re-emits a FEN so that equal positions produce equal strings: castling rights are
+/// written in `KQkq` order and dropped if the king or rook left its home square, the en passant
+/// square is only kept if a capture is possible, and the clocks are clamped to values that can
+/// actually be reached.
+pub fn normalize_fen(fen: &str) -> Result<String, anyhow::Error> {
+    let mut game = ChessGame::new_position(fen)?;
+
+    let rights = &mut game.castling_rights;
+    let board = &game.board;
+    for color in [White, Black] {
+        let rank = color.back_rank();
+        if (0..8).all(|f| board[Coord::new(f, rank)] != Some(King(color))) {
+            rights.remove_color(color);
+        }
+        for file in [rights.king_side_file, rights.queen_side_file] {
+            let c = Coord::new(file, rank);
+            if board[c] != Some(Rook(color)) {
+                rights.remove_for_square(c);
+            }
+        }
+    }
+
+    if !game.is_enpassant_available() {
+        game.enpassant_target_square = None;
+    }
+
+    game.fullmove_clock = game.fullmove_clock.max(1);
+    let plies_played = 2 * (game.fullmove_clock - 1) + (game.side_to_move == Black) as u32;
+    game.halfmove_clock = game.halfmove_clock.min(plies_played);
+
+    Ok(game.to_fen())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::*;
+    use crate::*;
+
+    #[test]
+    fn test_fen_roundtrip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/8/8/8/8/8/8/8 b - - 12 40",
+        ] {
+            assert_eq!(ChessGame::new_position(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_history() {
+        let start = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 30";
+        let mut game = ChessGame::new_position(start).unwrap();
+        assert_eq!(game.fen_history(), [start]);
+        for san in ["e4", "Kd7", "e5"] {
+            game.make_move(game.parse_san(san).unwrap()).unwrap();
+        }
+        let history = game.fen_history();
+        assert_eq!(history.len(), game.history.len() + 1);
+        assert_eq!(
+            history,
+            [
+                start,
+                "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 30",
+                "8/3k4/8/8/4P3/8/8/4K3 w - - 1 31",
+                "8/3k4/8/4P3/8/8/8/4K3 b - - 0 31",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clock_quirks() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        let game = ChessGame::new_position(&format!("{} 0 0", start)).unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_clock), (0, 1));
+        let game = ChessGame::new_position(&format!("{} -3 x", start)).unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_clock), (0, 1));
+        let game = ChessGame::new_position(&format!("{} 12 30", start)).unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_clock), (12, 30));
+    }
+
+    #[test]
+    fn test_from_placement() {
+        // White to move and mate in one with Qg7
+        let puzzle = ChessGame::from_placement("6k1/5p1p/5PpQ/8/8/8/8/6K1", White).unwrap();
+        assert_eq!(puzzle.to_fen(), "6k1/5p1p/5PpQ/8/8/8/8/6K1 w - - 0 1");
+        let mates = puzzle.mate_in_one();
+        assert_eq!(mates.len(), 1);
+        assert_eq!(puzzle.move_to_san(mates[0]), "Qg7#");
+
+        let black = ChessGame::from_placement("6k1/8/8/8/8/8/8/6K1", Black).unwrap();
+        assert_eq!(black.side_to_move, Black);
+        assert!(ChessGame::from_placement("6k1/8/8/8/8/8/6K1", White).is_err());
+        assert!(ChessGame::from_placement("6k1/8/8/8/8/8/8/6K1 w", White).is_err());
+    }
+
+    #[test]
+    fn test_from_epd() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        let game = ChessGame::from_epd(start).unwrap();
+        assert_eq!(game.to_fen(), ChessGame::new().to_fen());
+        let game = ChessGame::from_epd(&format!("{} bm e4; id \"start\";", start)).unwrap();
+        assert_eq!(game.fullmove_clock, 1);
+        let game = ChessGame::from_epd(&format!("{} hmvc 4; fmvn 0;", start)).unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_clock), (4, 1));
+        assert!(ChessGame::from_epd("rnbqkbnr/pppppppp w").is_err());
+    }
+
+    #[test]
+    fn test_fen_ranks() {
+        let game = ChessGame::new_position(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let ranks: Vec<String> = game.board.fen_ranks().collect();
+        assert_eq!(ranks.len(), 8);
+        assert_eq!(ranks[0], "r3k2r");
+        assert_eq!(ranks[7], "R3K2R");
+        assert_eq!(ranks.join("/"), game.board.to_fen_placement());
+    }
+
+    #[test]
+    fn test_fen_field_accessors() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(fen_side_to_move(fen).unwrap(), Black);
+        assert_eq!(
+            fen_placement(fen).unwrap(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR"
+        );
+        assert!(fen_side_to_move("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+        assert!(fen_placement("8/8/8/8/8/8/8/8 w").is_err());
+    }
+
+    #[test]
+    fn test_validate_fen() {
+        assert!(validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_ok());
+        assert!(
+            validate_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+                .is_ok()
+        );
+        for (fen, problem) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", "8 ranks"),
+            (
+                "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "invalid piece",
+            ),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "7 squares",
+            ),
+            (
+                "rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+                "0 kings",
+            ),
+            (
+                "rnbqkbnP/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQ - 0 1",
+                "pawn on rank 8",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1",
+                "without its rook",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e6 0 1",
+                "en passant",
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+                "invalid clock",
+            ),
+        ] {
+            let error = validate_fen(fen).unwrap_err().to_string();
+            assert!(error.contains(problem), "{}: {}", fen, error);
+        }
+        // the placement is read by the same parser as apply_fen
+        let bad_rank = "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(
+            validate_fen(bad_rank).unwrap_err().to_string(),
+            ChessGame::new_position(bad_rank).err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_normalize_fen() {
+        // the en passant square can't be used, and castling letters are out of order
+        let a =
+            normalize_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b qkQK e3 0 1").unwrap();
+        let b =
+            normalize_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(
+            a,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        // a usable en passant square is kept
+        let ep = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        assert_eq!(normalize_fen(ep).unwrap(), ep);
+        // rights without the rook at home, and an impossible halfmove clock
+        assert_eq!(
+            normalize_fen("4k3/8/8/8/8/8/8/4K2R w KQkq - 30 2").unwrap(),
+            "4k3/8/8/8/8/8/8/4K2R w K - 2 2"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// A small JSON summary of the game, written by hand so web frontends can use it without serde.
+//
+// Every value is null or a string of letters, digits, slashes, dashes and spaces (FEN, castling
+// letters, squares, UCI moves), so nothing needs escaping. The field names are part of the API:
+// don't rename them.
+
+use crate::color::Color::White;
+use crate::ChessGame;
+
+impl ChessGame {
+    /// The position as a compact JSON object, like
+    /// `{"fen":"...","sideToMove":"white","castling":"KQkq","enPassant":null,"legalMoves":["a2a3",...]}`.
+    /// The legal moves are in UCI, in the order of `legal_moves_sorted`.
+    pub fn to_json(&self) -> String {
+        let side = if self.side_to_move == White {
+            "white"
+        } else {
+            "black"
+        };
+        let en_passant = match self.enpassant_target_square {
+            Some(c) => format!("\"{}\"", c),
+            None => "null".to_string(),
+        };
+        let moves: Vec<String> = self
+            .legal_moves_sorted()
+            .iter()
+            .map(|m| format!("\"{}\"", m.to_uci()))
+            .collect();
+        format!(
+            "{{\"fen\":\"{}\",\"sideToMove\":\"{}\",\"castling\":\"{}\",\"enPassant\":{},\"legalMoves\":[{}]}}",
+            self.to_fen(),
+            side,
+            self.castling_rights,
+            en_passant,
+            moves.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_to_json() {
+        let game = ChessGame::new_position("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let json = game.to_json();
+        assert!(json.starts_with(
+            "{\"fen\":\"4k3/8/8/8/8/8/8/4K2R w K - 0 1\",\"sideToMove\":\"white\",\
+             \"castling\":\"K\",\"enPassant\":null,\"legalMoves\":[\"e1d1\","
+        ));
+        assert!(json.contains("\"e1g1\""));
+        assert!(json.ends_with("\"h1h8\"]}"));
+
+        let mut game = ChessGame::new();
+        game.play_san("e4").unwrap();
+        let json = game.to_json();
+        assert!(
+            json.contains("\"sideToMove\":\"black\",\"castling\":\"KQkq\",\"enPassant\":\"e3\"")
+        );
+        let moves = json.split("\"legalMoves\":[").nth(1).unwrap();
+        assert_eq!(moves.matches(',').count() + 1, 20);
+    }
+}
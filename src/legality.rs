@@ -0,0 +1,229 @@
+// Reasons a move is illegal, for interfaces that want to tell the user what went wrong.
+//
+// Move generation only answers whether a move is legal. `why_illegal` walks through the same
+// conditions one at a time and reports the first one the move fails.
+
+use core::fmt;
+
+use crate::movegen::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::moves::Move;
+use crate::piece::Piece::*;
+use crate::ChessGame;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalReason {
+    NoPieceAtSource,
+    /// The piece on the source square belongs to the side not on move.
+    WrongColor,
+    /// The piece recorded in the move isn't the one on the source square.
+    PieceMismatch,
+    OwnPieceOnTarget,
+    /// Another piece stands in the way of a slider, a pawn push or castling.
+    BlockedPath,
+    /// The piece doesn't move like that.
+    UnreachableTarget,
+    /// A pawn reaching the last rank without a valid promotion piece, or a promotion piece
+    /// given for a move that doesn't promote.
+    BadPromotion,
+    LeavesKingInCheck,
+    NoCastlingRight,
+    CastleOutOfCheck,
+    CastleThroughCheck,
+}
+
+impl fmt::Display for IllegalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            IllegalReason::NoPieceAtSource => "there is no piece on that square",
+            IllegalReason::WrongColor => "that piece belongs to the other side",
+            IllegalReason::PieceMismatch => "that piece isn't on that square",
+            IllegalReason::OwnPieceOnTarget => "that square holds one of your own pieces",
+            IllegalReason::BlockedPath => "another piece is in the way",
+            IllegalReason::UnreachableTarget => "that piece can't move there",
+            IllegalReason::BadPromotion => "the promotion piece is wrong",
+            IllegalReason::LeavesKingInCheck => "that would leave your king in check",
+            IllegalReason::NoCastlingRight => "you can't castle on that side anymore",
+            IllegalReason::CastleOutOfCheck => "you can't castle out of check",
+            IllegalReason::CastleThroughCheck => "the king can't castle through check",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl ChessGame {
+    /// Why `m` can't be played in the current position, or `None` if it's legal. Only the
+    /// squares, piece and promotion of the move are looked at; its flags are worked out from the
+    /// position, except that castling is recognized by the flag, by the king taking its own rook
+    /// or by the king moving two files.
+    pub fn why_illegal(&self, m: Move) -> Option<IllegalReason> {
+        let piece = match self.board[m.source] {
+            Some(p) => p,
+            None => return Some(IllegalReason::NoPieceAtSource),
+        };
+        if piece.get_color() != self.side_to_move {
+            return Some(IllegalReason::WrongColor);
+        }
+        if piece != m.piece {
+            return Some(IllegalReason::PieceMismatch);
+        }
+
+        let along_rank = matches!(piece, King(_)) && m.source.rank() == m.target.rank();
+        let takes_own_rook = along_rank && self.board[m.target] == Some(Rook(self.side_to_move));
+        let is_castling = m.castling
+            || takes_own_rook
+            || (along_rank && m.source.file().abs_diff(m.target.file()) > 1);
+        if is_castling {
+            // castling moves always land the king on the g or c file, but in Chess960 it may
+            // start on either side of that square, or already stand on it
+            let king_side = if takes_own_rook {
+                match self.castling_rights.side_of_rook_file(m.target.file()) {
+                    Some(king_side) => king_side,
+                    None => return Some(IllegalReason::NoCastlingRight),
+                }
+            } else if m.castling {
+                m.target.file() == 6
+            } else {
+                m.target.file() > m.source.file()
+            };
+            if let Some(reason) = self.castling_obstacle(m.source, self.side_to_move, king_side) {
+                return Some(reason);
+            }
+            return match self.castling_move(self.side_to_move, king_side) {
+                Some(castling) if castling.target == m.target || takes_own_rook => None,
+                Some(_) => Some(IllegalReason::UnreachableTarget),
+                None => Some(IllegalReason::LeavesKingInCheck),
+            };
+        }
+
+        let mut moves = vec![];
+        self.pseudo_legal_moves_from(m.source, &mut moves);
+        moves.retain(|g| g.target == m.target && !g.castling);
+        if moves.is_empty() {
+            if self.board[m.target].is_some_and(|p| p.get_color() == self.side_to_move) {
+                return Some(IllegalReason::OwnPieceOnTarget);
+            }
+            if self.is_blocked(m) {
+                return Some(IllegalReason::BlockedPath);
+            }
+            return Some(IllegalReason::UnreachableTarget);
+        }
+        match moves.iter().find(|g| g.promoted_piece == m.promoted_piece) {
+            None => Some(IllegalReason::BadPromotion),
+            Some(g) if !self.is_king_safe_after(g) => Some(IllegalReason::LeavesKingInCheck),
+            Some(_) => None,
+        }
+    }
+
+    /// Returns true if `m` would be a valid slider move or pawn push on an empty board.
+    fn is_blocked(&self, m: Move) -> bool {
+        let reach = match m.piece {
+            Bishop(_) => bishop_attacks(m.source, 0),
+            Rook(_) => rook_attacks(m.source, 0),
+            Queen(_) => queen_attacks(m.source, 0),
+            Pawn(color) => {
+                let steps =
+                    (m.target.rank() as i32 - m.source.rank() as i32) * color.pawn_direction();
+                let double = m.source.rank() == color.pawn_start_rank();
+                return m.source.file() == m.target.file()
+                    && (steps == 1 || (steps == 2 && double));
+            }
+            _ => return false,
+        };
+        reach & (1 << m.target.to_usize()) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::legality::IllegalReason::*;
+    use crate::*;
+
+    fn reason(fen: &str, uci: &str) -> Option<legality::IllegalReason> {
+        let game = ChessGame::new_position(fen).unwrap();
+        let source: board::Coord = uci[0..2].parse().unwrap();
+        let target: board::Coord = uci[2..4].parse().unwrap();
+        let piece = game.board[source].unwrap_or(piece::Piece::Pawn(game.side_to_move));
+        let promotion = uci
+            .chars()
+            .nth(4)
+            .and_then(piece::Piece::new_from_fen_char)
+            .map(|p| p.with_color(game.side_to_move));
+        game.why_illegal(moves::Move::new(source, target, piece, promotion))
+    }
+
+    #[test]
+    fn test_why_illegal() {
+        let start = STARTING_FEN;
+        assert_eq!(reason(start, "e2e4"), None);
+        assert_eq!(reason(start, "e3e4"), Some(NoPieceAtSource));
+        assert_eq!(reason(start, "e7e5"), Some(WrongColor));
+        assert_eq!(reason(start, "d1d2"), Some(OwnPieceOnTarget));
+        assert_eq!(reason(start, "f1c4"), Some(BlockedPath));
+        assert_eq!(reason(start, "g1g3"), Some(UnreachableTarget));
+        let blocked_pawn = "4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1";
+        assert_eq!(reason(blocked_pawn, "e2e4"), Some(BlockedPath));
+        assert_eq!(reason(blocked_pawn, "e2e5"), Some(UnreachableTarget));
+
+        let promotion = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(reason(promotion, "a7a8q"), None);
+        assert_eq!(reason(promotion, "a7a8"), Some(BadPromotion));
+        assert_eq!(reason(promotion, "e1e2q"), Some(BadPromotion));
+
+        // the bishop on d2 is pinned by the rook on d8
+        let pinned = "3rk3/8/8/8/8/8/3B4/3K4 w - - 0 1";
+        assert_eq!(reason(pinned, "d2e3"), Some(LeavesKingInCheck));
+        assert_eq!(reason(pinned, "d1e1"), None);
+        assert_eq!(reason(pinned, "d1d3"), Some(UnreachableTarget));
+
+        let castling = "r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1";
+        assert_eq!(reason(castling, "e1g1"), None);
+        assert_eq!(reason(castling, "e1c1"), Some(NoCastlingRight));
+        let checked = "r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1";
+        assert_eq!(reason(checked, "e1g1"), Some(CastleOutOfCheck));
+        let through = "r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1";
+        assert_eq!(reason(through, "e1g1"), Some(CastleThroughCheck));
+        assert_eq!(reason(through, "e1c1"), None);
+        let knight_in_way = "r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1";
+        assert_eq!(reason(knight_in_way, "e1c1"), Some(BlockedPath));
+
+        assert_eq!(
+            LeavesKingInCheck.to_string(),
+            "that would leave your king in check"
+        );
+    }
+
+    #[test]
+    fn test_why_illegal_agrees_with_legal_moves() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // Chess960: the king castles one file to the right on the queen side, and stays on
+            // g1 on the king side
+            "rk4r1/8/8/8/8/8/8/RK4R1 w GAga - 0 1",
+            "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            assert!(game.legal_moves().iter().any(|m| m.castling), "{}", fen);
+            for m in game.legal_moves() {
+                assert_eq!(game.why_illegal(m), None, "{} in {}", m.to_uci(), fen);
+            }
+        }
+        // castling written as the king taking its rook
+        let game = ChessGame::new_position("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+        let king_takes_rook = moves::Move::new(
+            "g1".parse().unwrap(),
+            "h1".parse().unwrap(),
+            piece::Piece::King(Color::White),
+            None,
+        );
+        assert_eq!(game.why_illegal(king_takes_rook), None);
+        // only the castling rook can be taken
+        let game = ChessGame::new_position("4k3/8/8/8/8/8/8/5KRR w G - 0 1").unwrap();
+        let takes_h1 = moves::Move::new(
+            "f1".parse().unwrap(),
+            "h1".parse().unwrap(),
+            piece::Piece::King(Color::White),
+            None,
+        );
+        assert_eq!(game.why_illegal(takes_h1), Some(NoCastlingRight));
+    }
+}
@@ -9,14 +9,34 @@ use anyhow::*;
 
 pub mod board;
 pub mod castling;
+pub mod check;
+pub mod chess960;
+pub mod clock;
 pub mod color;
+pub mod fen;
+pub mod json;
+pub mod legality;
+pub mod material;
+pub mod movegen;
 pub mod moves;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
+pub mod polyglot;
+pub mod pst;
+#[cfg(test)]
+mod random_games;
+pub mod san;
+pub mod status;
+pub mod uci;
+pub mod zobrist;
 
 use board::*;
 use castling::*;
 use color::Color::{self, Black, White};
+use moves::{HistoryEntry, Move};
 use piece::Piece;
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct ChessGame {
@@ -26,18 +46,31 @@ pub struct ChessGame {
     pub enpassant_target_square: Option<Coord>,
     pub halfmove_clock: u32,
     pub fullmove_clock: u32,
+    pub history: Vec<HistoryEntry>,
+    /// Running Zobrist hash of the position, see `hash()`.
+    hash: u64,
+    /// Time left for each side, for games played on a clock.
+    pub clock: Option<clock::ClockState>,
+    /// How many times each position (by Zobrist hash) has appeared in the game.
+    repetitions: HashMap<u64, u8>,
 }
 
 impl Default for ChessGame {
     fn default() -> Self {
-        Self {
+        let mut game = Self {
             board: Board::new(),
             castling_rights: CastlingRights::new(),
             side_to_move: White,
             enpassant_target_square: None,
             halfmove_clock: 0,
             fullmove_clock: 1,
-        }
+            history: vec![],
+            hash: 0,
+            clock: None,
+            repetitions: HashMap::new(),
+        };
+        game.reset_repetitions();
+        game
     }
 }
 
@@ -80,81 +113,699 @@ impl ChessGame {
         Ok(game)
     }
 
+    /// Removes every piece from the board, keeping the rest of the game state.
     pub fn clear(&mut self) {
         self.board.clear();
+        self.reset_repetitions();
     }
 
-    pub fn apply_fen(&mut self, fen: &str) -> Result<(), anyhow::Error> {
-        //TODO: We should be able to feed non-FEN strings and get an error!
-        //
-        let mut fen_fields = fen.split_ascii_whitespace();
-        if fen_fields.clone().count() != 6 {
-            return Err(anyhow!(""));
-        }
-        // for each field if we can't read it correctly, use default setting
-        // piece placement
+    /// Restores the standard starting position, with full castling rights and fresh clocks.
+    pub fn reset(&mut self) {
+        self.set_start_position();
+    }
+
+    /// Empties the game completely: no pieces, no castling rights, no history, White to move.
+    pub fn clear_full(&mut self) {
         self.board.clear();
-        let piece_placement = fen_fields.next().unwrap();
-        self.board.set_position_from_fen(piece_placement)?;
-        // fill the piece list too!
-        // side to move
-        let side_to_move = fen_fields.next().unwrap();
-        self.side_to_move = match side_to_move {
+        self.castling_rights = CastlingRights::none();
+        self.side_to_move = White;
+        self.enpassant_target_square = None;
+        self.halfmove_clock = 0;
+        self.fullmove_clock = 1;
+        self.history.clear();
+        self.clock = None;
+        self.reset_repetitions();
+    }
+
+    /// Forgets the positions seen so far, counting only the current one. Also refreshes the
+    /// running hash, as it's called whenever the position is set from scratch.
+    fn reset_repetitions(&mut self) {
+        self.refresh_hash();
+        self.repetitions.clear();
+        self.repetitions.insert(self.hash, 1);
+    }
+
+    /// Sets up the position described by a FEN string. Every field is checked against its
+    /// grammar before anything changes, so the game is left untouched on error.
+    pub fn apply_fen(&mut self, fen: &str) -> Result<(), anyhow::Error> {
+        let fields = fen::fen_fields(fen)?;
+
+        let mut board = self.board.clone();
+        board.clear();
+        board.set_position_from_fen(fields[0])?;
+
+        let side_to_move = match fields[1] {
             "w" | "W" => White,
             "b" | "B" => Black,
-            _ => White,
+            other => return Err(anyhow!("invalid side to move {}", other)),
         };
 
-        // Castling rights
-        let castl = fen_fields.next().unwrap();
-        self.castling_rights.white_king_side = castl.find('K') != None;
-        self.castling_rights.white_queen_side = castl.find('Q') != None;
-        self.castling_rights.black_king_side = castl.find('k') != None;
-        self.castling_rights.black_queen_side = castl.find('q') != None;
-
-        // en passant target square
-        let en_passant = fen_fields.next().unwrap();
-        match en_passant {
-            "-" => self.enpassant_target_square = None,
-            _ => {
-                let mut it = en_passant.chars();
-                let file = it.next().unwrap();
-                let rank = it.next().unwrap();
-
-                self.enpassant_target_square = Some(Coord::new(
-                    match file {
-                        'a' => 0,
-                        'b' => 1,
-                        'c' => 2,
-                        'd' => 3,
-                        'e' => 4,
-                        'f' => 5,
-                        'g' => 6,
-                        'h' => 7,
-                        _ => return Err(anyhow!("")),
-                    },
-                    match rank {
-                        '3' => 3,
-                        '6' => 6,
-                        _ => return Err(anyhow!("")),
-                    },
-                ));
+        // KQkq, or rook files in Shredder-FEN
+        let castling = fields[2];
+        let valid_right = |c: char| matches!(c.to_ascii_lowercase(), 'k' | 'q' | 'a'..='h');
+        if castling != "-" && !castling.chars().all(valid_right) {
+            return Err(anyhow!("invalid castling rights {}", castling));
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                // the square behind a pawn the opponent just double pushed: on the 3rd rank
+                // when Black is to move and on the 6th when White is (ranks are zero-indexed)
+                let expected_rank = if side_to_move == White { 5 } else { 2 };
+                let square = square
+                    .parse::<Coord>()
+                    .ok()
+                    .filter(|c| c.rank() == expected_rank)
+                    .ok_or_else(|| anyhow!("invalid en passant square {}", square))?;
+                Some(square)
             }
         };
 
-        let half_move_clock = fen_fields.next().unwrap_or("0");
-        self.halfmove_clock = half_move_clock.parse::<u32>().unwrap_or(0);
-        let full_move_count = fen_fields.next().unwrap_or("1");
-        self.fullmove_clock = full_move_count.parse::<u32>().unwrap_or(0);
+        // some databases write a move number of 0 or clocks that aren't numbers: fall back to
+        // the defaults rather than numbering the moves wrong
+        let halfmove_clock = fields[4].parse::<u32>().unwrap_or(0);
+        let fullmove_clock = fields[5].parse::<u32>().unwrap_or(1).max(1);
 
+        self.board = board;
+        self.history.clear();
+        self.side_to_move = side_to_move;
+        self.castling_rights = CastlingRights::none();
+        if castling != "-" {
+            for c in castling.chars() {
+                self.add_castling_right(c);
+            }
+        }
+        self.enpassant_target_square = en_passant;
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_clock = fullmove_clock;
+
+        self.reset_repetitions();
         Ok(())
     }
 
+    /// Reads one castling letter: KQkq, or a rook file in Shredder-FEN for Chess960. KQkq refer
+    /// to the outermost rook on that side of the king.
+    fn add_castling_right(&mut self, c: char) {
+        let color = if c.is_ascii_uppercase() { White } else { Black };
+        let rank = color.back_rank();
+        let king_file = (0..8)
+            .find(|f| self.board[Coord::new(*f, rank)] == Some(Piece::King(color)))
+            .unwrap_or(4);
+        let is_rook = |f: &usize| self.board[Coord::new(*f, rank)] == Some(Piece::Rook(color));
+        let (king_side, file) = match c.to_ascii_lowercase() {
+            'k' => (true, (king_file..8).rev().find(is_rook).unwrap_or(7)),
+            'q' => (false, (0..king_file).find(is_rook).unwrap_or(0)),
+            f @ 'a'..='h' => {
+                let file = f as usize - 'a' as usize;
+                (file > king_file, file)
+            }
+            _ => return,
+        };
+        let rights = &mut self.castling_rights;
+        match (color, king_side) {
+            (White, true) => rights.white_king_side = true,
+            (White, false) => rights.white_queen_side = true,
+            (Black, true) => rights.black_king_side = true,
+            (Black, false) => rights.black_queen_side = true,
+        }
+        if king_side {
+            rights.king_side_file = file;
+        } else {
+            rights.queen_side_file = file;
+        }
+    }
+
     fn set_start_position(&mut self) {
         self.apply_fen(STARTING_FEN).ok();
     }
 
     pub fn set_piece(&mut self, coord: Coord, piece: Piece) {
         self.board[coord] = Some(piece);
+        // the piece may also change whether en passant is available
+        self.refresh_hash();
+    }
+}
+
+impl ChessGame {
+    /// Returns true if the piece recorded in the move is the one standing on its source square.
+    pub fn verify_move_piece(&self, m: Move) -> bool {
+        self.board[m.source] == Some(m.piece)
+    }
+
+    /// Checks that a pawn promotes exactly when it reaches its promotion rank, and only to a
+    /// queen, rook, bishop or knight of its own color.
+    fn verify_promotion(&self, m: Move) -> Result<(), anyhow::Error> {
+        let color = m.piece.get_color();
+        let reaches_last_rank =
+            matches!(m.piece, Piece::Pawn(_)) && m.target.rank() == color.promotion_rank();
+        match m.promoted_piece {
+            None if reaches_last_rank => Err(anyhow!("{} needs a promotion piece", m.to_uci())),
+            None => Ok(()),
+            Some(_) if !reaches_last_rank => Err(anyhow!(
+                "{} promotes without a pawn reaching the last rank",
+                m.to_uci()
+            )),
+            Some(Piece::King(_)) | Some(Piece::Pawn(_)) => {
+                Err(anyhow!("{} can't promote to a king or pawn", m.to_uci()))
+            }
+            Some(p) if p.get_color() != color => Err(anyhow!(
+                "{} promotes to a piece of the wrong color",
+                m.to_uci()
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Applies a move to the game, updating all the metadata and recording it in the history.
+    /// The move is not checked for legality, but malformed promotions are rejected.
+    pub fn make_move(&mut self, m: Move) -> Result<(), anyhow::Error> {
+        let piece = match self.board[m.source] {
+            Some(p) => p,
+            None => return Err(anyhow!("no piece to move on {}", m.source)),
+        };
+        if !self.verify_move_piece(m) {
+            return Err(anyhow!(
+                "move {} is for {:?} but {} holds {:?}",
+                m.to_uci(),
+                m.piece,
+                m.source,
+                piece
+            ));
+        }
+        self.verify_promotion(m)?;
+        let hash = self.hash;
+        // squares whose piece may change, which is the whole back rank when castling
+        let mut touched = [None; 8];
+        if m.castling {
+            for (file, square) in touched.iter_mut().enumerate() {
+                *square = Some(Coord::new(file, m.source.rank()));
+            }
+        } else {
+            touched[0] = Some(m.source);
+            touched[1] = Some(m.target);
+            if m.enpassant {
+                touched[2] = Some(Coord::new(m.target.file(), m.source.rank()));
+            }
+        }
+        let before = touched.map(|c| c.and_then(|c| self.board[c]));
+        self.hash ^= self.enpassant_key() ^ zobrist::castling_key(&self.castling_rights);
+
+        let captured = self.board.apply_move(m);
+        for (c, old) in touched.iter().zip(before) {
+            if let Some(c) = *c {
+                if let Some(p) = old {
+                    self.xor_piece(c, p);
+                }
+                if let Some(p) = self.board[c] {
+                    self.xor_piece(c, p);
+                }
+            }
+        }
+        self.history.push(HistoryEntry {
+            mv: m,
+            captured,
+            castling_rights: self.castling_rights,
+            enpassant_target_square: self.enpassant_target_square,
+            halfmove_clock: self.halfmove_clock,
+            hash,
+        });
+
+        if let Piece::King(color) = piece {
+            self.castling_rights.remove_color(color);
+        }
+        self.castling_rights.remove_for_square(m.source);
+        self.castling_rights.remove_for_square(m.target);
+
+        self.enpassant_target_square = if m.double_push {
+            m.target.en_passant_target(piece.get_color())
+        } else {
+            None
+        };
+
+        if matches!(piece, Piece::Pawn(_)) || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.side_to_move == Black {
+            self.fullmove_clock += 1;
+        }
+        self.side_to_move = self.side_to_move.opponent();
+        self.hash ^= zobrist::side_key()
+            ^ zobrist::castling_key(&self.castling_rights)
+            ^ self.enpassant_key();
+        *self.repetitions.entry(self.hash).or_insert(0) += 1;
+        Ok(())
+    }
+
+    /// Takes back the last move in the history, restoring the previous state exactly. Returns
+    /// the move taken back, or `None` if there's no history.
+    ///
+    /// Together with `make_move` this lets a search walk the tree on a single game instead of
+    /// cloning it for every node: for each move, `make_move`, search the child, then
+    /// `unmake_move` before trying the next one.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        if let Some(count) = self.repetitions.get_mut(&self.hash) {
+            *count -= 1;
+            if *count == 0 {
+                self.repetitions.remove(&self.hash);
+            }
+        }
+        self.hash = entry.hash;
+
+        let m = entry.mv;
+        if m.castling {
+            let rank = m.source.rank();
+            let (rook_file, rook_to) = if m.target.file() == 6 {
+                (entry.castling_rights.king_side_file, 5)
+            } else {
+                (entry.castling_rights.queen_side_file, 3)
+            };
+            let rook = self.board[Coord::new(rook_to, rank)].take();
+            let king = self.board[m.target].take();
+            self.board[m.source] = king;
+            self.board[Coord::new(rook_file, rank)] = rook;
+        } else {
+            self.board[m.target] = None;
+            self.board[m.source] = Some(m.piece);
+            if m.enpassant {
+                self.board[Coord::new(m.target.file(), m.source.rank())] = entry.captured;
+            } else {
+                self.board[m.target] = entry.captured;
+            }
+        }
+
+        self.castling_rights = entry.castling_rights;
+        self.enpassant_target_square = entry.enpassant_target_square;
+        self.halfmove_clock = entry.halfmove_clock;
+        self.side_to_move = self.side_to_move.opponent();
+        if self.side_to_move == Black {
+            self.fullmove_clock -= 1;
+        }
+        Some(m)
+    }
+
+    /// How many times the current position has appeared in the game, including now.
+    pub fn repetition_count(&self) -> u8 {
+        self.repetitions.get(&self.hash).copied().unwrap_or(1)
+    }
+
+    /// Every position (by Zobrist hash) that appeared more than once in the game, with how many
+    /// times, in the order they first appeared.
+    pub fn find_repetitions(&self) -> Vec<(u64, u8)> {
+        let mut seen: Vec<(u64, u8)> = vec![];
+        let hashes = self.history.iter().map(|e| e.hash);
+        for hash in hashes.chain(std::iter::once(self.hash)) {
+            match seen.iter_mut().find(|(h, _)| *h == hash) {
+                Some((_, count)) => *count += 1,
+                None => seen.push((hash, 1)),
+            }
+        }
+        seen.retain(|(_, count)| *count > 1);
+        seen
+    }
+
+    /// Returns a copy of the game with the move applied, leaving this one untouched.
+    pub fn with_move(&self, m: Move) -> Result<ChessGame, anyhow::Error> {
+        let mut game = self.clone();
+        game.make_move(m)?;
+        Ok(game)
+    }
+
+    /// Returns true if both games are in the same position as far as the rules go: same pieces,
+    /// side to move, castling rights and en passant capture, whatever the clocks say. An en
+    /// passant square no pawn can capture on doesn't count.
+    pub fn same_position_as(&self, other: &ChessGame) -> bool {
+        let rights = |g: &ChessGame| {
+            let r = &g.castling_rights;
+            (
+                r.available_for(White),
+                r.available_for(Black),
+                r.king_side_file,
+                r.queen_side_file,
+            )
+        };
+        let en_passant = |g: &ChessGame| {
+            g.enpassant_target_square
+                .filter(|_| g.is_enpassant_available())
+        };
+        self.board.same_position(&other.board)
+            && self.side_to_move == other.side_to_move
+            && rights(self) == rights(other)
+            && en_passant(self) == en_passant(other)
+    }
+
+    /// The ply at which two move lists first differ, which is the length of the shorter one if
+    /// it's a prefix of the other. From the standard start, ply `p` is move `p / 2 + 1`, played
+    /// by White if `p` is even.
+    pub fn divergence(a_history: &[Move], b_history: &[Move]) -> usize {
+        a_history
+            .iter()
+            .zip(b_history)
+            .take_while(|(a, b)| a == b)
+            .count()
+    }
+
+    /// Returns a copy of the game after playing `moves` in order, leaving this one untouched.
+    /// Fails on the first move that isn't legal, giving its index in the slice.
+    pub fn position_after(&self, moves: &[Move]) -> Result<ChessGame, anyhow::Error> {
+        let mut game = self.clone();
+        for (i, m) in moves.iter().enumerate() {
+            if !game.legal_moves().contains(m) {
+                return Err(anyhow!("move {} ({}) is not legal", i, m.to_uci()));
+            }
+            game.make_move(*m)?;
+        }
+        Ok(game)
+    }
+
+    /// Pieces taken so far, as (captured by White, captured by Black).
+    pub fn captured_pieces(&self) -> (Vec<Piece>, Vec<Piece>) {
+        let mut by_white = vec![];
+        let mut by_black = vec![];
+        for captured in self.history.iter().filter_map(|e| e.captured) {
+            if captured.get_color() == Black {
+                by_white.push(captured);
+            } else {
+                by_black.push(captured);
+            }
+        }
+        (by_white, by_black)
+    }
+
+    /// Material won by White minus material won by Black, in pawns. Positive means White is up.
+    pub fn material_difference(&self) -> i32 {
+        let (by_white, by_black) = self.captured_pieces();
+        by_white.iter().map(Piece::value).sum::<i32>()
+            - by_black.iter().map(Piece::value).sum::<i32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::Move;
+    use crate::piece::Piece::*;
+    use crate::*;
+
+    fn mv(game: &ChessGame, m: &str) -> Move {
+        let source: Coord = m[0..2].parse().unwrap();
+        let target: Coord = m[2..4].parse().unwrap();
+        Move::new(source, target, game.board[source].unwrap(), None)
+    }
+
+    #[test]
+    fn test_reset_and_clear_full() {
+        let mut game = ChessGame::new_position("8/8/4k3/8/8/3K4/8/8 b - - 7 40").unwrap();
+        game.reset();
+        assert_eq!(game.to_fen(), STARTING_FEN);
+        let m = mv(&game, "e2e4");
+        game.make_move(m).unwrap();
+        game.clear_full();
+        assert_eq!(game.to_fen(), "8/8/8/8/8/8/8/8 w - - 0 1");
+        assert!(game.history.is_empty());
+    }
+
+    #[test]
+    fn test_double_push_sets_en_passant() {
+        let mut game = ChessGame::new();
+        let m = Move::new_pawn_double_push(White, "e2".parse().unwrap());
+        game.make_move(m).unwrap();
+        assert_eq!(game.enpassant_target_square, "e3".parse().ok());
+        let m = Move::new_pawn_push(Black, "a7".parse().unwrap());
+        game.make_move(m).unwrap();
+        assert_eq!(game.enpassant_target_square, None);
+    }
+
+    #[test]
+    fn test_en_passant_fen_roundtrip() {
+        // after 1. d4 the target is d3, and after 1. e4 d5 it's d6
+        let after_d4 = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1";
+        let after_d5 = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        for fen in [after_d4, after_d5] {
+            let game = ChessGame::new_position(fen).unwrap();
+            assert_eq!(game.to_fen(), fen);
+        }
+        let mut game = ChessGame::new();
+        game.make_move(game.parse_san("d4").unwrap()).unwrap();
+        assert_eq!(game.to_fen(), after_d4);
+        let mut game = ChessGame::new();
+        game.make_move(game.parse_san("e4").unwrap()).unwrap();
+        game.make_move(game.parse_san("d5").unwrap()).unwrap();
+        assert_eq!(game.to_fen(), after_d5);
+        // the target must be behind a pawn of the side that just moved
+        assert!(ChessGame::new_position(
+            "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d3 0 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_apply_fen_rejects_garbage() {
+        let mut game = ChessGame::new();
+        game.make_move(game.parse_san("e4").unwrap()).unwrap();
+        let fen = game.to_fen();
+        let error = game
+            .apply_fen("hello there how are you today")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "placement needs 8 ranks, found 1");
+        // nothing was applied
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.history.len(), 1);
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        for (fen, problem) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+                "invalid piece X",
+            ),
+            (
+                "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "invalid piece 9",
+            ),
+            (
+                "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "rank 6 has 7",
+            ),
+            (&format!("{} white KQkq - 0 1", start), "side to move"),
+            (&format!("{} w yes - 0 1", start), "castling rights"),
+            (&format!("{} w KQkq e9 0 1", start), "en passant"),
+        ] {
+            let error = game.apply_fen(fen).err().unwrap().to_string();
+            assert!(error.contains(problem), "{}: {}", fen, error);
+        }
+        assert_eq!(game.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_unmake_move() {
+        let mut game =
+            ChessGame::new_position("r3k2r/8/8/3pP3/8/8/1p6/R3K2R w KQkq d6 0 1").unwrap();
+        let fen = game.to_fen();
+        for m in game.legal_moves() {
+            game.make_move(m).unwrap();
+            for reply in game.legal_moves() {
+                let after = game.to_fen();
+                game.make_move(reply).unwrap();
+                assert_eq!(game.unmake_move(), Some(reply));
+                assert_eq!(game.to_fen(), after);
+            }
+            assert_eq!(game.unmake_move(), Some(m));
+            assert_eq!(game.to_fen(), fen);
+        }
+        assert_eq!(game.unmake_move(), None);
+    }
+
+    #[test]
+    fn test_make_unmake_random_games() {
+        let mut picker = random_games::MovePicker::new(0x2545f4914f6cdd1d);
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ] {
+            let start = ChessGame::new_position(fen).unwrap();
+            for _ in 0..1000 {
+                let mut seen = vec![];
+                let mut game = picker.play(&start, 60, |g| {
+                    seen.push((g.to_fen(), g.zobrist_hash(), g.repetition_count()))
+                });
+                // the last position is the current one
+                seen.pop();
+                while let Some((fen, hash, repetitions)) = seen.pop() {
+                    assert!(game.unmake_move().is_some());
+                    assert_eq!(game.to_fen(), fen);
+                    assert_eq!(game.zobrist_hash(), hash);
+                    assert_eq!(game.repetition_count(), repetitions);
+                }
+                assert!(game.history.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stale_move_rejected() {
+        let mut game = ChessGame::new();
+        let stale = mv(&game, "g1f3");
+        assert!(game.verify_move_piece(stale));
+        game.apply_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBRN w - - 0 1")
+            .unwrap();
+        assert!(!game.verify_move_piece(stale));
+        assert!(game.make_move(stale).is_err());
+        assert!(game.history.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_promotions_rejected() {
+        let mut game = ChessGame::new_position("4k3/P7/8/8/8/8/6P1/4K3 w - - 0 1").unwrap();
+        let fen = game.to_fen();
+        let a7: Coord = "a7".parse().unwrap();
+        let g2: Coord = "g2".parse().unwrap();
+        for bad in [
+            Move::new_promotion(White, a7, King(White)),
+            Move::new_promotion(White, a7, Pawn(White)),
+            Move::new_promotion(White, a7, Queen(Black)),
+            Move::new_promotion(White, g2, Queen(White)),
+            Move::new_pawn_push(White, a7),
+        ] {
+            assert!(game.make_move(bad).is_err(), "{}", bad.to_uci());
+            assert_eq!(game.to_fen(), fen);
+        }
+        game.make_move(Move::new_promotion(White, a7, Queen(White)))
+            .unwrap();
+        assert_eq!(game.to_fen(), "Q3k3/8/8/8/8/8/6P1/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn test_castling_moves_the_rook() {
+        let placement = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R";
+        let castles = [
+            (
+                "w",
+                "O-O",
+                "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 b kq - 1 1",
+            ),
+            (
+                "w",
+                "O-O-O",
+                "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/2KR3R b kq - 1 1",
+            ),
+            (
+                "b",
+                "O-O",
+                "r4rk1/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQ - 1 2",
+            ),
+            (
+                "b",
+                "O-O-O",
+                "2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQ - 1 2",
+            ),
+        ];
+        for (side, san, after) in castles {
+            let fen = format!("{} {} KQkq - 0 1", placement, side);
+            let mut game = ChessGame::new_position(&fen).unwrap();
+            assert!(game.play_san(san).unwrap().castling);
+            assert_eq!(game.to_fen(), after, "{} {}", side, san);
+            game.unmake_move().unwrap();
+            assert_eq!(game.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_same_position_as() {
+        let play = |sans: &[&str]| {
+            let mut game = ChessGame::new();
+            for san in sans {
+                game.play_san(san).unwrap();
+            }
+            game
+        };
+        let a = play(&["Nf3", "Nf6", "Nc3", "Nc6"]);
+        let b = play(&["Nc3", "Nc6", "Nf3", "Nf6"]);
+        assert!(a.same_position_as(&b));
+        // the clocks don't matter
+        let mut c = b.clone();
+        c.halfmove_clock = 0;
+        c.fullmove_clock = 40;
+        assert!(a.same_position_as(&c));
+        // but castling rights do
+        let d = play(&["Nf3", "Nf6", "Rg1", "Nc6", "Rh1", "Ng8", "Nc3", "Nf6"]);
+        assert!(a.board.same_position(&d.board));
+        assert!(!a.same_position_as(&d));
+        // and an en passant square only when a pawn can take on it
+        let e4 = play(&["e4"]);
+        let mut no_ep = e4.clone();
+        no_ep.enpassant_target_square = None;
+        assert!(e4.same_position_as(&no_ep));
+    }
+
+    #[test]
+    fn test_divergence() {
+        let play = |sans: &[&str]| {
+            let mut game = ChessGame::new();
+            for san in sans {
+                game.make_move(game.parse_san(san).unwrap()).unwrap();
+            }
+            game.history.iter().map(|e| e.mv).collect::<Vec<Move>>()
+        };
+        let spanish = play(&["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        let italian = play(&["e4", "e5", "Nf3", "Nc6", "Bc4"]);
+        // they part ways on White's third move
+        assert_eq!(ChessGame::divergence(&spanish, &italian), 4);
+        assert_eq!(ChessGame::divergence(&spanish, &spanish[..3]), 3);
+        assert_eq!(ChessGame::divergence(&spanish, &[]), 0);
+    }
+
+    #[test]
+    fn test_position_after() {
+        let game = ChessGame::new();
+        let e4 = game.parse_san("e4").unwrap();
+        let e5 = game.with_move(e4).unwrap().parse_san("e5").unwrap();
+        let after = game.position_after(&[e4, e5]).unwrap();
+        assert_eq!(
+            after.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        assert_eq!(game.to_fen(), ChessGame::new().to_fen());
+        // White can't play twice in a row
+        let error = game.position_after(&[e4, e5, e5]).err().unwrap();
+        assert!(error.to_string().starts_with("move 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        let mut game = ChessGame::new();
+        assert_eq!(game.repetition_count(), 1);
+        for _ in 0..2 {
+            for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                let m = mv(&game, m);
+                game.make_move(m).unwrap();
+            }
+        }
+        assert_eq!(game.repetition_count(), 3);
+        // the start position three times, the other three positions of the shuffle twice
+        let repetitions = game.find_repetitions();
+        assert_eq!(repetitions.len(), 4);
+        assert_eq!(repetitions[0], (ChessGame::new().zobrist_hash(), 3));
+        assert!(repetitions[1..].iter().all(|(_, count)| *count == 2));
+        game.unmake_move();
+        assert_eq!(game.repetition_count(), 2);
+        game.reset();
+        assert_eq!(game.repetition_count(), 1);
+    }
+
+    #[test]
+    fn test_captured_pieces() {
+        let mut game = ChessGame::new();
+        for m in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5d2", "c1d2"] {
+            let m = mv(&game, m);
+            game.make_move(m).unwrap();
+        }
+        let (by_white, by_black) = game.captured_pieces();
+        assert_eq!(by_white, vec![Pawn(Black), Queen(Black)]);
+        assert_eq!(by_black, vec![Pawn(White), Pawn(White)]);
+        assert_eq!(game.material_difference(), 8);
     }
 }
@@ -0,0 +1,134 @@
+// Material counts and imbalances.
+//
+// Plain material counting says a knight for a bishop is an even trade; the imbalance keeps track
+// of which pieces each side has so displays can tell those positions apart.
+
+use core::fmt;
+
+use crate::board::Board;
+use crate::color::Color::{self, Black, White};
+use crate::piece::Piece::{self, *};
+
+/// The kinds of pieces, in the order used by `PieceCounts`.
+const KINDS: [fn(Color) -> Piece; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
+
+/// Number of pieces of each kind, indexed as pawn, knight, bishop, rook, queen, king.
+pub type PieceCounts = [u32; 6];
+
+/// Position of the kind of `p` in a `PieceCounts`.
+pub fn kind_index(p: Piece) -> usize {
+    match p {
+        Pawn(_) => 0,
+        Knight(_) => 1,
+        Bishop(_) => 2,
+        Rook(_) => 3,
+        Queen(_) => 4,
+        King(_) => 5,
+    }
+}
+
+impl Board {
+    /// How many pieces of each kind `color` has on the board.
+    pub fn piece_counts(&self, color: Color) -> PieceCounts {
+        let mut counts = [0; 6];
+        for p in (0..64).filter_map(|t| self[t]) {
+            if p.get_color() == color {
+                counts[kind_index(p)] += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn imbalance(&self) -> Imbalance {
+        Imbalance {
+            white: self.piece_counts(White),
+            black: self.piece_counts(Black),
+        }
+    }
+}
+
+/// The pieces each side has, compared kind by kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Imbalance {
+    pub white: PieceCounts,
+    pub black: PieceCounts,
+}
+
+impl Imbalance {
+    /// White's count minus Black's count for the kind of `piece`. Its color is ignored.
+    pub fn difference(&self, piece: Piece) -> i32 {
+        let i = kind_index(piece);
+        self.white[i] as i32 - self.black[i] as i32
+    }
+
+    /// Returns true if both sides have the same pieces.
+    pub fn is_balanced(&self) -> bool {
+        self.white == self.black
+    }
+
+    /// Returns true if `color` has two or more bishops and the opponent doesn't.
+    pub fn has_bishop_pair(&self, color: Color) -> bool {
+        let (own, other) = if color == White {
+            (self.white, self.black)
+        } else {
+            (self.black, self.white)
+        };
+        own[2] >= 2 && other[2] < 2
+    }
+}
+
+/// Summarizes the imbalance from White's side, like "White: +1 knight, -1 bishop", followed by
+/// the side with the bishop pair if any.
+impl fmt::Display for Imbalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_balanced() {
+            return write!(f, "balanced");
+        }
+        let differences: Vec<String> = KINDS[..5]
+            .iter()
+            .map(|kind| kind(White))
+            .filter(|p| self.difference(*p) != 0)
+            .map(|p| format!("{:+} {}", self.difference(p), p.get_name()))
+            .collect();
+        if differences.is_empty() {
+            write!(f, "balanced")?;
+        } else {
+            write!(f, "White: {}", differences.join(", "))?;
+        }
+        for color in [White, Black] {
+            if self.has_bishop_pair(color) {
+                write!(f, "; {} has the bishop pair", color.name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_imbalance() {
+        let game = ChessGame::new();
+        let imbalance = game.board.imbalance();
+        assert_eq!(imbalance.white, [8, 2, 2, 2, 1, 1]);
+        assert!(imbalance.is_balanced());
+        assert_eq!(imbalance.to_string(), "balanced");
+
+        // White has a knight for a bishop, leaving Black with the bishop pair
+        let game = ChessGame::new_position(
+            "r1bqkb1r/pppp1ppp/2n5/4p3/4P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let imbalance = game.board.imbalance();
+        assert_eq!(imbalance.difference(piece::Piece::Knight(Color::Black)), 1);
+        assert_eq!(imbalance.difference(piece::Piece::Bishop(Color::White)), -1);
+        assert!(imbalance.has_bishop_pair(Color::Black));
+        assert!(!imbalance.has_bishop_pair(Color::White));
+        assert_eq!(
+            imbalance.to_string(),
+            "White: +1 knight, -1 bishop; Black has the bishop pair"
+        );
+    }
+}
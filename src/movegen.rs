@@ -0,0 +1,1289 @@
+// Move generation for ChessGame.
+//
+// Moves are generated pseudo-legally straight from the board representation, and then filtered
+// with the checks and pins of the position (see `check.rs`). King moves and en passant captures
+// are still played on a copy of the board to see if the king is left in check.
+
+use crate::board::*;
+use crate::check::CheckInfo;
+use crate::color::Color;
+use crate::legality::IllegalReason;
+use crate::material::kind_index;
+use crate::moves::Move;
+use crate::piece::Piece::{self, *};
+use crate::ChessGame;
+use std::collections::HashMap;
+
+pub const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_OFFSETS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+pub const ROOK_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+pub const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+const PROMOTION_PIECES: [fn(Color) -> Piece; 4] = [Queen, Rook, Bishop, Knight];
+
+/// The squares a slider on `from` attacks along `directions`, as a bitset of tiles. Each ray
+/// stops at the first occupied square of `occupancy`, which is included whatever its color.
+/// This is the plain reference version a magic bitboard lookup would be checked against.
+pub fn sliding_attacks(from: Coord, occupancy: u64, directions: &[(i32, i32)]) -> u64 {
+    let mut attacks = 0;
+    for (df, dr) in directions {
+        for sq in from.ray(*df, *dr) {
+            attacks |= 1 << sq.to_usize();
+            if occupancy & (1 << sq.to_usize()) != 0 {
+                break;
+            }
+        }
+    }
+    attacks
+}
+
+pub fn rook_attacks(from: Coord, occupancy: u64) -> u64 {
+    sliding_attacks(from, occupancy, &ROOK_DIRECTIONS)
+}
+
+pub fn bishop_attacks(from: Coord, occupancy: u64) -> u64 {
+    sliding_attacks(from, occupancy, &BISHOP_DIRECTIONS)
+}
+
+pub fn queen_attacks(from: Coord, occupancy: u64) -> u64 {
+    rook_attacks(from, occupancy) | bishop_attacks(from, occupancy)
+}
+
+/// The squares the pawns of `color` attack, as a bitset of tiles.
+pub fn pawn_attacks(board: &Board, color: Color) -> u64 {
+    let mut attacks = 0;
+    for t in (0..64).filter(|t| board[*t] == Some(Pawn(color))) {
+        for df in [-1, 1] {
+            if let Some(sq) = Coord::from_tile(t).offset(df, color.pawn_direction()) {
+                attacks |= 1 << sq.to_usize();
+            }
+        }
+    }
+    attacks
+}
+
+/// The occupied squares of the board, as a bitset of tiles.
+pub fn occupancy(board: &Board) -> u64 {
+    (0..64)
+        .filter(|t| board[*t].is_some())
+        .fold(0, |bits, t| bits | 1 << t)
+}
+
+/// Returns true if any piece of color `by` attacks the square `c` on the given board.
+pub fn is_attacked(board: &Board, c: Coord, by: Color) -> bool {
+    // pawns attack diagonally forward, so look backwards from the target square
+    let pawn_dr = -by.pawn_direction();
+    for df in [-1, 1] {
+        if let Some(sq) = c.offset(df, pawn_dr) {
+            if board[sq] == Some(Pawn(by)) {
+                return true;
+            }
+        }
+    }
+    for (df, dr) in KNIGHT_OFFSETS {
+        if let Some(sq) = c.offset(df, dr) {
+            if board[sq] == Some(Knight(by)) {
+                return true;
+            }
+        }
+    }
+    for (df, dr) in KING_OFFSETS {
+        if let Some(sq) = c.offset(df, dr) {
+            if board[sq] == Some(King(by)) {
+                return true;
+            }
+        }
+    }
+    for (directions, slider) in [(ROOK_DIRECTIONS, Rook(by)), (BISHOP_DIRECTIONS, Bishop(by))] {
+        for (df, dr) in directions {
+            let mut sq = c;
+            while let Some(next) = sq.offset(df, dr) {
+                sq = next;
+                if let Some(p) = board[sq] {
+                    if p == slider || p == Queen(by) {
+                        return true;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Every square holding a piece of color `by` that attacks the square `c` on the given board.
+pub fn attackers_of(board: &Board, c: Coord, by: Color) -> Vec<Coord> {
+    let mut attackers = vec![];
+    let pawn_dr = -by.pawn_direction();
+    let jumps = [(-1, pawn_dr, Pawn(by)), (1, pawn_dr, Pawn(by))]
+        .into_iter()
+        .chain(KNIGHT_OFFSETS.iter().map(|(df, dr)| (*df, *dr, Knight(by))))
+        .chain(KING_OFFSETS.iter().map(|(df, dr)| (*df, *dr, King(by))));
+    for (df, dr, attacker) in jumps {
+        if let Some(sq) = c.offset(df, dr) {
+            if board[sq] == Some(attacker) {
+                attackers.push(sq);
+            }
+        }
+    }
+    for (directions, slider) in [(ROOK_DIRECTIONS, Rook(by)), (BISHOP_DIRECTIONS, Bishop(by))] {
+        for (df, dr) in directions {
+            let mut sq = c;
+            while let Some(next) = sq.offset(df, dr) {
+                sq = next;
+                if let Some(p) = board[sq] {
+                    if p == slider || p == Queen(by) {
+                        attackers.push(sq);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    attackers
+}
+
+/// Finds the king of the given color on the board.
+pub fn find_king(board: &Board, color: Color) -> Option<Coord> {
+    (0..64)
+        .find(|t| board[*t] == Some(King(color)))
+        .map(Coord::from_tile)
+}
+
+impl ChessGame {
+    /// All moves for the side to move, without checking if they leave the king in check.
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        for tile in 0..64 {
+            self.pseudo_legal_moves_from(Coord::from_tile(tile), &mut moves);
+        }
+        moves
+    }
+
+    /// Pseudo-legal moves for the side that is not to move, as if it were its turn. They aren't
+    /// checked for legality and never include en passant, so they are only good for things like
+    /// pre-move input or the opponent's mobility.
+    pub fn opponent_pseudo_moves(&self) -> Vec<Move> {
+        let mut flipped = self.clone();
+        flipped.side_to_move = self.side_to_move.opponent();
+        flipped.enpassant_target_square = None;
+        flipped.pseudo_legal_moves()
+    }
+
+    /// Number of moves `color` has: legal moves for the side to move, and pseudo-legal moves
+    /// (see `opponent_pseudo_moves`) for the other side, since legality is only defined for the
+    /// side whose turn it is.
+    pub fn mobility(&self, color: Color) -> usize {
+        self.mobility_moves(color).len()
+    }
+
+    /// `mobility` split by the kind of piece moving, indexed like `material::PieceCounts`.
+    pub fn mobility_by_piece(&self, color: Color) -> [usize; 6] {
+        let mut counts = [0; 6];
+        for m in self.mobility_moves(color) {
+            counts[kind_index(m.piece)] += 1;
+        }
+        counts
+    }
+
+    fn mobility_moves(&self, color: Color) -> Vec<Move> {
+        if color == self.side_to_move {
+            self.legal_moves()
+        } else {
+            self.opponent_pseudo_moves()
+        }
+    }
+
+    /// Pushes the pseudo-legal moves of the side to move's piece on `from`, if there is one.
+    pub(crate) fn pseudo_legal_moves_from(&self, from: Coord, moves: &mut Vec<Move>) {
+        self.generate_from(from, false, moves);
+    }
+
+    /// The move generator itself. With `captures_only` it skips quiet moves and castling, but
+    /// still generates promotions.
+    fn generate_from(&self, from: Coord, captures_only: bool, moves: &mut Vec<Move>) {
+        let color = self.side_to_move;
+        let wanted = |capture: bool| capture || !captures_only;
+        match self.board[from] {
+            Some(Pawn(c)) if c == color => self.pawn_moves(from, color, captures_only, moves),
+            Some(Knight(c)) if c == color => {
+                for (df, dr) in KNIGHT_OFFSETS {
+                    if let Some((to, capture)) = self.free_or_enemy(from, df, dr) {
+                        if wanted(capture) {
+                            moves.push(Move::new_knight_move(from, to, color, capture));
+                        }
+                    }
+                }
+            }
+            Some(Bishop(c)) if c == color => {
+                for (to, capture) in self.slide(from, &BISHOP_DIRECTIONS) {
+                    if wanted(capture) {
+                        moves.push(Move::new_bishop_move(from, to, color, capture));
+                    }
+                }
+            }
+            Some(Rook(c)) if c == color => {
+                for (to, capture) in self.slide(from, &ROOK_DIRECTIONS) {
+                    if wanted(capture) {
+                        moves.push(Move::new_rook_move(from, to, color, capture));
+                    }
+                }
+            }
+            Some(Queen(c)) if c == color => {
+                for (to, capture) in self.slide(from, &ROOK_DIRECTIONS) {
+                    if wanted(capture) {
+                        moves.push(Move::new(from, to, Queen(color), None).capture(capture));
+                    }
+                }
+                for (to, capture) in self.slide(from, &BISHOP_DIRECTIONS) {
+                    if wanted(capture) {
+                        moves.push(Move::new(from, to, Queen(color), None).capture(capture));
+                    }
+                }
+            }
+            Some(King(c)) if c == color => {
+                for (df, dr) in KING_OFFSETS {
+                    if let Some((to, capture)) = self.free_or_enemy(from, df, dr) {
+                        if wanted(capture) {
+                            moves.push(Move::new(from, to, King(color), None).capture(capture));
+                        }
+                    }
+                }
+                if !captures_only {
+                    self.castling_moves(from, color, moves);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the displaced square if it's empty or holds an enemy piece, along with a capture flag.
+    fn free_or_enemy(&self, from: Coord, df: i32, dr: i32) -> Option<(Coord, bool)> {
+        let to = from.offset(df, dr)?;
+        match self.board[to] {
+            None => Some((to, false)),
+            Some(p) if p.get_color() != self.side_to_move => Some((to, true)),
+            _ => None,
+        }
+    }
+
+    /// Walks every direction until the edge of the board or the first piece found.
+    fn slide(&self, from: Coord, directions: &[(i32, i32)]) -> Vec<(Coord, bool)> {
+        let mut targets = vec![];
+        for (df, dr) in directions {
+            let mut sq = from;
+            while let Some(to) = sq.offset(*df, *dr) {
+                sq = to;
+                match self.board[to] {
+                    None => targets.push((to, false)),
+                    Some(p) => {
+                        if p.get_color() != self.side_to_move {
+                            targets.push((to, true));
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        targets
+    }
+
+    fn pawn_moves(&self, from: Coord, color: Color, captures_only: bool, moves: &mut Vec<Move>) {
+        let dr = color.pawn_direction();
+        let (start_rank, last_rank) = (color.pawn_start_rank(), color.promotion_rank());
+        // pushes
+        if let Some(to) = from.offset(0, dr) {
+            if self.board[to].is_none() {
+                if to.rank() == last_rank {
+                    for promoted in PROMOTION_PIECES {
+                        moves.push(Move::new_promotion(color, from, promoted(color)));
+                    }
+                } else if !captures_only {
+                    moves.push(Move::new_pawn_push(color, from));
+                    if from.rank() == start_rank
+                        && to.offset(0, dr).is_some_and(|t| self.board[t].is_none())
+                    {
+                        moves.push(Move::new_pawn_double_push(color, from));
+                    }
+                }
+            }
+        }
+        // captures
+        for df in [-1, 1] {
+            if let Some(to) = from.offset(df, dr) {
+                match self.board[to] {
+                    Some(p) if p.get_color() != color => {
+                        if to.rank() == last_rank {
+                            for promoted in PROMOTION_PIECES {
+                                moves.push(
+                                    Move::new(from, to, Pawn(color), Some(promoted(color)))
+                                        .capture(true),
+                                );
+                            }
+                        } else {
+                            moves.push(Move::new(from, to, Pawn(color), None).capture(true));
+                        }
+                    }
+                    None if self.enpassant_target_square == Some(to) => {
+                        moves.push(
+                            Move::new(from, to, Pawn(color), None)
+                                .capture(true)
+                                .enpassant(true),
+                        );
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn castling_moves(&self, from: Coord, color: Color, moves: &mut Vec<Move>) {
+        let rank = color.back_rank();
+        for (king_side, king_to) in [(true, 6), (false, 2)] {
+            if self.castling_obstacle(from, color, king_side).is_none() {
+                let rook_file = if king_side {
+                    self.castling_rights.king_side_file
+                } else {
+                    self.castling_rights.queen_side_file
+                };
+                let (target, rook) = (Coord::new(king_to, rank), Coord::new(rook_file, rank));
+                moves.push(Move::new_castling(from, target, rook, color));
+            }
+        }
+    }
+
+    /// Why the king of `color` on `from` can't castle to the given side, or `None` if it can.
+    /// Whether the king ends up in check behind its own rook, as can happen in Chess960, is
+    /// left to the legality test.
+    pub(crate) fn castling_obstacle(
+        &self,
+        from: Coord,
+        color: Color,
+        king_side: bool,
+    ) -> Option<IllegalReason> {
+        let rank = color.back_rank();
+        let (king_side_right, queen_side_right) = self.castling_rights.available_for(color);
+        let (allowed, rook_file, king_to, rook_to) = if king_side {
+            (king_side_right, self.castling_rights.king_side_file, 6, 5)
+        } else {
+            (queen_side_right, self.castling_rights.queen_side_file, 2, 3)
+        };
+        if !allowed
+            || from.rank() != rank
+            || self.board[Coord::new(rook_file, rank)] != Some(Rook(color))
+        {
+            return Some(IllegalReason::NoCastlingRight);
+        }
+        if is_attacked(&self.board, from, color.opponent()) {
+            return Some(IllegalReason::CastleOutOfCheck);
+        }
+        let span = |a: usize, b: usize| a.min(b)..=a.max(b);
+        // everything the king and rook go through must be empty, except for themselves
+        let path_clear = span(from.file(), king_to)
+            .chain(span(rook_file, rook_to))
+            .filter(|f| *f != from.file() && *f != rook_file)
+            .all(|f| self.board[Coord::new(f, rank)].is_none());
+        if !path_clear {
+            return Some(IllegalReason::BlockedPath);
+        }
+        let path_safe = span(from.file(), king_to)
+            .all(|f| !is_attacked(&self.board, Coord::new(f, rank), color.opponent()));
+        if !path_safe {
+            return Some(IllegalReason::CastleThroughCheck);
+        }
+        None
+    }
+
+    /// Returns true if playing the pseudo-legal move `m` doesn't leave the mover's king in check.
+    pub(crate) fn is_king_safe_after(&self, m: &Move) -> bool {
+        let color = m.piece.get_color();
+        let mut board = self.board.clone();
+        board.apply_move(*m);
+        match find_king(&board, color) {
+            Some(king) => !is_attacked(&board, king, color.opponent()),
+            None => true,
+        }
+    }
+
+    /// Returns true if the pseudo-legal move `m` is legal, using the checks and pins in `info`
+    /// when they are enough to tell.
+    fn is_legal_with(&self, info: &CheckInfo, m: &Move) -> bool {
+        info.allows(m).unwrap_or_else(|| self.is_king_safe_after(m))
+    }
+
+    /// All legal moves for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let info = self.check_info();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|m| self.is_legal_with(&info, m))
+            .collect()
+    }
+
+    /// Same as `legal_moves`, but in a fixed order: by source tile, then target tile (a1 = 0,
+    /// h8 = 63), then promotion piece (knight, bishop, rook, queen). Use it when the output
+    /// must be reproducible; `legal_moves` order may change between versions.
+    pub fn legal_moves_sorted(&self) -> Vec<Move> {
+        let mut moves = self.legal_moves();
+        moves.sort();
+        moves
+    }
+
+    /// The legal captures, en passant captures and promotions of the side to move: the moves a
+    /// quiescence search looks at. Quiet moves are never generated.
+    pub fn legal_captures(&self) -> Vec<Move> {
+        let info = self.check_info();
+        let mut moves = vec![];
+        for tile in 0..64 {
+            self.generate_from(Coord::from_tile(tile), true, &mut moves);
+        }
+        moves.retain(|m| self.is_legal_with(&info, m));
+        moves
+    }
+
+    /// The legal moves of the side to move's pieces of the same kind as `kind`, whose color is
+    /// ignored. Only squares holding such a piece are generated from.
+    pub fn legal_moves_for_piece_type(&self, kind: Piece) -> Vec<Move> {
+        let piece = kind.with_color(self.side_to_move);
+        let info = self.check_info();
+        let mut moves = vec![];
+        for tile in (0..64).filter(|t| self.board[*t] == Some(piece)) {
+            self.pseudo_legal_moves_from(Coord::from_tile(tile), &mut moves);
+        }
+        moves.retain(|m| self.is_legal_with(&info, m));
+        moves
+    }
+
+    /// All legal promotions of the side to move, one move per promotion piece.
+    pub fn promotion_moves(&self) -> Vec<Move> {
+        let mut moves = self.legal_captures();
+        moves.retain(|m| m.promoted_piece.is_some());
+        moves
+    }
+
+    /// Returns true if the side to move has at least one legal move. Stops at the first piece
+    /// that has one, so it's much cheaper than generating the whole move list.
+    pub fn has_legal_move(&self) -> bool {
+        let info = self.check_info();
+        let mut moves = vec![];
+        for tile in 0..64 {
+            moves.clear();
+            self.pseudo_legal_moves_from(Coord::from_tile(tile), &mut moves);
+            if moves.iter().any(|m| self.is_legal_with(&info, m)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Legal moves of the side to move, grouped by the square they start from. Squares without
+    /// legal moves are left out.
+    pub fn legal_moves_by_square(&self) -> HashMap<Coord, Vec<Move>> {
+        let mut grouped: HashMap<Coord, Vec<Move>> = HashMap::new();
+        for m in self.legal_moves() {
+            grouped.entry(m.source).or_default().push(m);
+        }
+        grouped
+    }
+
+    /// Highlights the squares the piece on `from` can legally move to, as a selection of `color`
+    /// on `board`. Captures go in a second selection of the same color at half brightness, so
+    /// they stand out. Nothing is added for squares without a movable piece.
+    pub fn highlight_legal_targets(&self, from: Coord, board: &mut Board, color: SelectionColor) {
+        let pawn = matches!(self.board[from], Some(Pawn(_)));
+        let (captures, quiet): (Vec<Coord>, Vec<Coord>) =
+            self.legal_targets(from).into_iter().partition(|t| {
+                self.board[*t].is_some() || (pawn && self.enpassant_target_square == Some(*t))
+            });
+        if !quiet.is_empty() {
+            board.add_selection(Selection::from_coords(&quiet, color));
+        }
+        if !captures.is_empty() {
+            board.add_selection(Selection::from_coords(&captures, color.darker()));
+        }
+    }
+
+    /// If the side to move is in check, adds a red selection on `board` covering its king and
+    /// the pieces giving check. Does nothing otherwise.
+    pub fn highlight_check(&self, board: &mut Board) {
+        let info = self.check_info();
+        if let (true, Some(king)) = (info.is_in_check(), self.find_king(self.side_to_move)) {
+            let mut squares = vec![king];
+            squares.extend(info.checkers);
+            board.add_selection(Selection::from_coords(
+                &squares,
+                SelectionColor::new(255, 0, 0),
+            ));
+        }
+    }
+
+    /// Returns true if `c` holds a piece of the side to move, i.e. if clicking it should start a
+    /// move. The piece may still have no legal moves.
+    pub fn can_select(&self, c: Coord) -> bool {
+        self.board[c].is_some_and(|p| p.get_color() == self.side_to_move)
+    }
+
+    /// The squares the piece on `from` can legally move to. Empty if the square doesn't hold a
+    /// piece of the side to move.
+    pub fn legal_targets(&self, from: Coord) -> Vec<Coord> {
+        let info = self.check_info();
+        let mut moves = vec![];
+        self.pseudo_legal_moves_from(from, &mut moves);
+        let mut targets: Vec<Coord> = vec![];
+        for m in moves.iter().filter(|m| self.is_legal_with(&info, m)) {
+            // promotions generate one move per piece on the same target
+            if !targets.contains(&m.target) {
+                targets.push(m.target);
+            }
+        }
+        targets
+    }
+
+    /// Returns true if moving from `from` to `to` is a legal pawn move that needs a promotion
+    /// piece, so a UI knows it has to ask for one before completing the move.
+    pub fn pending_promotion(&self, from: Coord, to: Coord) -> bool {
+        let mut moves = vec![];
+        self.pseudo_legal_moves_from(from, &mut moves);
+        moves
+            .iter()
+            .any(|m| m.target == to && m.promoted_piece.is_some() && self.is_king_safe_after(m))
+    }
+
+    /// Builds the legal promotion from `from` to `to` with the chosen piece. Only the kind of
+    /// piece is taken from `choice`, its color is always the side to move's.
+    pub fn complete_promotion(
+        &self,
+        from: Coord,
+        to: Coord,
+        choice: Piece,
+    ) -> Result<Move, anyhow::Error> {
+        let promoted = choice.with_color(self.side_to_move);
+        if !PROMOTION_PIECES
+            .iter()
+            .any(|p| p(self.side_to_move) == promoted)
+        {
+            return Err(anyhow::anyhow!("can't promote to {:?}", choice));
+        }
+        let mut moves = vec![];
+        self.pseudo_legal_moves_from(from, &mut moves);
+        moves
+            .into_iter()
+            .find(|m| {
+                m.target == to && m.promoted_piece == Some(promoted) && self.is_king_safe_after(m)
+            })
+            .ok_or_else(|| anyhow::anyhow!("{}{} is not a legal promotion", from, to))
+    }
+
+    /// Builds the legal move from `from` to `to`, with the capture, double push, en passant and
+    /// castling flags worked out from the position. Castling is given as the king's move to the
+    /// g or c file. `promotion` is required for promotions and must be `None` otherwise; only its
+    /// kind is used.
+    pub fn move_between(
+        &self,
+        from: Coord,
+        to: Coord,
+        promotion: Option<Piece>,
+    ) -> Result<Move, anyhow::Error> {
+        let promoted = promotion.map(|p| p.with_color(self.side_to_move));
+        let mut moves = vec![];
+        self.pseudo_legal_moves_from(from, &mut moves);
+        let info = self.check_info();
+        let candidates: Vec<Move> = moves
+            .into_iter()
+            .filter(|m| m.target == to && self.is_legal_with(&info, m))
+            .collect();
+        if promoted.is_none() && candidates.iter().any(|m| m.promoted_piece.is_some()) {
+            return Err(anyhow::anyhow!("{}{} needs a promotion piece", from, to));
+        }
+        candidates
+            .into_iter()
+            .find(|m| m.promoted_piece == promoted)
+            .ok_or_else(|| anyhow::anyhow!("{}{} is not a legal move", from, to))
+    }
+
+    /// Returns true if the side to move has a pawn that can (pseudo-legally) capture en passant.
+    pub fn is_enpassant_available(&self) -> bool {
+        let ep = match self.enpassant_target_square {
+            Some(ep) => ep,
+            None => return false,
+        };
+        let color = self.side_to_move;
+        let dr = -color.pawn_direction();
+        [-1, 1]
+            .iter()
+            .filter_map(|df| ep.offset(*df, dr))
+            .any(|sq| self.board[sq] == Some(Pawn(color)))
+    }
+
+    /// The square and piece that `m` captures, if any. For en passant that's the pawn beside the
+    /// mover, not the empty target square.
+    pub fn capture_target(&self, m: Move) -> Option<(Coord, Piece)> {
+        if m.castling {
+            return None;
+        }
+        let square = if m.enpassant {
+            Coord::new(m.target.file(), m.source.rank())
+        } else {
+            m.target
+        };
+        self.board[square]
+            .filter(|p| p.get_color() != m.piece.get_color())
+            .map(|p| (square, p))
+    }
+
+    /// The castling move for the given color and side, if it's legal right now. Only the side to
+    /// move can castle, so this is always `None` for the other color.
+    pub fn castling_move(&self, color: Color, king_side: bool) -> Option<Move> {
+        if color != self.side_to_move {
+            return None;
+        }
+        let king = self.find_king(color)?;
+        let mut moves = vec![];
+        self.castling_moves(king, color, &mut moves);
+        let target_file = if king_side { 6 } else { 2 };
+        moves
+            .into_iter()
+            .find(|m| m.target.file() == target_file && self.is_king_safe_after(m))
+    }
+
+    /// Rebuilds a move packed with `Move::to_packed`. Returns `None` if it isn't legal here.
+    pub fn move_from_packed(&self, packed: u16) -> Option<Move> {
+        let source = Coord::from_tile((packed & 0x3f) as usize);
+        self.legal_moves()
+            .into_iter()
+            .find(|m| m.source == source && m.to_packed() == packed)
+    }
+
+    pub fn find_king(&self, color: Color) -> Option<Coord> {
+        find_king(&self.board, color)
+    }
+
+    /// Returns true if the side to move is in check.
+    pub fn is_in_check(&self) -> bool {
+        match self.find_king(self.side_to_move) {
+            Some(king) => is_attacked(&self.board, king, self.side_to_move.opponent()),
+            None => false,
+        }
+    }
+
+    pub fn is_square_attacked(&self, c: Coord, by: Color) -> bool {
+        is_attacked(&self.board, c, by)
+    }
+
+    /// The squares `piece` would attack if it stood on `c`, whatever is there now, in tile
+    /// order. Sliders are stopped by the current pieces; the one on `c`, if any, is ignored.
+    pub fn attacks_from_as(&self, c: Coord, piece: Piece) -> Vec<Coord> {
+        let occupancy = occupancy(&self.board) & !(1 << c.to_usize());
+        let offsets = |offsets: &[(i32, i32)]| {
+            offsets
+                .iter()
+                .filter_map(|(df, dr)| c.offset(*df, *dr))
+                .fold(0u64, |bits, sq| bits | 1 << sq.to_usize())
+        };
+        let attacks = match piece {
+            Pawn(color) => offsets(&[(-1, color.pawn_direction()), (1, color.pawn_direction())]),
+            Knight(_) => offsets(&KNIGHT_OFFSETS),
+            Bishop(_) => bishop_attacks(c, occupancy),
+            Rook(_) => rook_attacks(c, occupancy),
+            Queen(_) => queen_attacks(c, occupancy),
+            King(_) => offsets(&KING_OFFSETS),
+        };
+        (0..64)
+            .filter(|t| attacks & (1 << t) != 0)
+            .map(Coord::from_tile)
+            .collect()
+    }
+
+    /// The squares of the pieces of color `by` that attack `c`.
+    pub fn attackers_of(&self, c: Coord, by: Color) -> Vec<Coord> {
+        attackers_of(&self.board, c, by)
+    }
+
+    /// Space: the squares in the opponent's half of the board (ranks 5 to 8 for White) that
+    /// `color`'s pawns attack and the opponent's pawns don't.
+    pub fn space(&self, color: Color) -> usize {
+        let white_half: u64 = 0xffff_ffff;
+        let opponent_half = if color == Color::White {
+            !white_half
+        } else {
+            white_half
+        };
+        let safe = pawn_attacks(&self.board, color) & !pawn_attacks(&self.board, color.opponent());
+        (safe & opponent_half).count_ones() as usize
+    }
+
+    /// How many pieces attack the square, as (White attackers, Black attackers). Only direct
+    /// attacks count: a rook behind another rook on the same file isn't counted.
+    pub fn pressure(&self, c: Coord) -> (usize, usize) {
+        (
+            self.attackers_of(c, Color::White).len(),
+            self.attackers_of(c, Color::Black).len(),
+        )
+    }
+
+    /// The squares the piece on `c` guards, whatever stands on them: unlike its move targets
+    /// this includes squares held by its own pieces, and pawns only guard diagonally. Empty if
+    /// the square is empty.
+    pub fn defended_squares(&self, c: Coord) -> Vec<Coord> {
+        let piece = match self.board[c] {
+            Some(p) => p,
+            None => return vec![],
+        };
+        let steps = |offsets: &[(i32, i32)]| -> Vec<Coord> {
+            offsets
+                .iter()
+                .filter_map(|(df, dr)| c.offset(*df, *dr))
+                .collect()
+        };
+        let rays = |directions: &[(i32, i32)]| -> Vec<Coord> {
+            let mut squares = vec![];
+            for (df, dr) in directions {
+                let mut sq = c;
+                while let Some(next) = sq.offset(*df, *dr) {
+                    sq = next;
+                    squares.push(sq);
+                    if self.board[sq].is_some() {
+                        break;
+                    }
+                }
+            }
+            squares
+        };
+        match piece {
+            Pawn(color) => {
+                let dr = color.pawn_direction();
+                steps(&[(-1, dr), (1, dr)])
+            }
+            Knight(_) => steps(&KNIGHT_OFFSETS),
+            King(_) => steps(&KING_OFFSETS),
+            Bishop(_) => rays(&BISHOP_DIRECTIONS),
+            Rook(_) => rays(&ROOK_DIRECTIONS),
+            Queen(_) => [rays(&ROOK_DIRECTIONS), rays(&BISHOP_DIRECTIONS)].concat(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movegen::{bishop_attacks, occupancy, queen_attacks, rook_attacks};
+    use crate::moves::Move;
+    use crate::piece::Piece;
+    use crate::*;
+
+    fn coords(names: &[&str]) -> Vec<Coord> {
+        names.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_start_position_moves() {
+        let game = ChessGame::new();
+        assert_eq!(game.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn test_legal_moves_by_square() {
+        let grouped = ChessGame::new().legal_moves_by_square();
+        assert_eq!(grouped.len(), 10);
+        for file in 0..8 {
+            assert_eq!(grouped[&Coord::new(file, 1)].len(), 2);
+        }
+        assert_eq!(grouped[&"b1".parse().unwrap()].len(), 2);
+        assert_eq!(grouped[&"g1".parse().unwrap()].len(), 2);
+    }
+
+    #[test]
+    fn test_has_legal_move() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            "7k/5Q2/6K1/8/8/8/8/8 w - - 0 1",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            assert_eq!(game.has_legal_move(), !game.legal_moves().is_empty());
+        }
+    }
+
+    #[test]
+    #[ignore = "timing, run with --release --ignored --nocapture"]
+    fn bench_has_legal_move_terminal() {
+        use std::hint::black_box;
+        use std::time::Instant;
+        // in checkmate and stalemate there is no early exit, so every move is looked at
+        for fen in [
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            let start = Instant::now();
+            for _ in 0..100_000 {
+                black_box(black_box(&game).has_legal_move());
+            }
+            let has_legal_move = start.elapsed();
+            let start = Instant::now();
+            for _ in 0..100_000 {
+                black_box(!black_box(&game).legal_moves().is_empty());
+            }
+            let legal_moves = start.elapsed();
+            println!(
+                "{}: has_legal_move {:?}, legal_moves {:?}",
+                fen, has_legal_move, legal_moves
+            );
+        }
+    }
+
+    #[test]
+    fn test_packed_moves_roundtrip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            // Chess960: O-O-O and Kc1 both take the king from b1 to c1
+            "4k3/8/8/8/8/8/8/RK6 w A - 0 1",
+            "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BN1KR w HFhf - 2 9",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            for m in game.legal_moves() {
+                assert_eq!(game.move_from_packed(m.to_packed()), Some(m), "{}", fen);
+            }
+        }
+        // e2e5 isn't legal
+        let game = ChessGame::new();
+        assert_eq!(game.move_from_packed(12 | 36 << 6), None);
+    }
+
+    #[test]
+    fn test_castling_move() {
+        let game = ChessGame::new_position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let e1: Coord = "e1".parse().unwrap();
+        assert_eq!(
+            game.castling_move(White, true),
+            Some(Move::new_castling(
+                e1,
+                "g1".parse().unwrap(),
+                "h1".parse().unwrap(),
+                White
+            ))
+        );
+        assert_eq!(
+            game.castling_move(White, false),
+            Some(Move::new_castling(
+                e1,
+                "c1".parse().unwrap(),
+                "a1".parse().unwrap(),
+                White
+            ))
+        );
+        assert_eq!(game.castling_move(Black, true), None);
+        // the f1 square is attacked, and b1 is blocked
+        let game = ChessGame::new_position("r3k2r/8/8/8/8/8/5r2/RN2K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.castling_move(White, true), None);
+        assert_eq!(game.castling_move(White, false), None);
+    }
+
+    #[test]
+    fn test_promotion_moves() {
+        // the b7 pawn can push to b8 or take on a8
+        let game = ChessGame::new_position("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotions = game.promotion_moves();
+        assert_eq!(promotions.len(), 8);
+        assert_eq!(promotions.iter().filter(|m| m.capture).count(), 4);
+        assert!(ChessGame::new().promotion_moves().is_empty());
+    }
+
+    #[test]
+    fn test_legal_captures() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            let legal = game.legal_moves();
+            let mut captures = game.legal_captures();
+            captures.sort();
+            let mut expected: Vec<Move> = legal
+                .into_iter()
+                .filter(|m| m.capture || m.promoted_piece.is_some())
+                .collect();
+            expected.sort();
+            assert_eq!(captures, expected, "{}", fen);
+        }
+        assert!(ChessGame::new().legal_captures().is_empty());
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        // taking on d3 would remove both pawns from the fourth rank and expose the king to the rook
+        let game = ChessGame::new_position("8/8/8/8/k2Pp2R/8/8/4K3 b - d3 0 1").unwrap();
+        assert!(game.is_enpassant_available());
+        assert!(game.pseudo_legal_moves().iter().any(|m| m.enpassant));
+        assert!(!game.legal_moves().iter().any(|m| m.enpassant));
+        assert!(game.parse_san("exd3").is_err());
+        // without the rook the capture is fine
+        let game = ChessGame::new_position("8/8/8/8/k2Pp3/8/8/4K3 b - d3 0 1").unwrap();
+        assert!(game.legal_moves().iter().any(|m| m.enpassant));
+    }
+
+    #[test]
+    fn test_pressure() {
+        // d5 is hit by the e4 pawn and c3 knight, and guarded by the e6 pawn and f6 knight, while
+        // the d7 pawn blocks the queen
+        let game = ChessGame::new_position(
+            "rnbqkb1r/pppp1ppp/4pn2/8/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 3",
+        )
+        .unwrap();
+        let d5 = "d5".parse().unwrap();
+        assert_eq!(game.pressure(d5), (2, 2));
+        let mut attackers = game.attackers_of(d5, Black);
+        attackers.sort_by_key(|c| c.to_usize());
+        assert_eq!(attackers, coords(&["e6", "f6"]));
+        assert_eq!(game.pressure("b4".parse().unwrap()), (0, 1));
+    }
+
+    #[test]
+    fn test_defended_squares() {
+        // the rook on a1 guards its own pawn on a4, but not what's behind it
+        let game = ChessGame::new_position("4k3/8/8/8/P7/8/8/R3K3 w - - 0 1").unwrap();
+        let defended = game.defended_squares("a1".parse().unwrap());
+        assert!(defended.contains(&"a4".parse().unwrap()));
+        assert!(!defended.contains(&"a5".parse().unwrap()));
+        assert!(defended.contains(&"e1".parse().unwrap()));
+        assert_eq!(defended.len(), 7);
+        // the pawn guards b5 but not a5, where it would move
+        let pawn = game.defended_squares("a4".parse().unwrap());
+        assert_eq!(pawn, coords(&["b5"]));
+        assert!(game.defended_squares("h8".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_opponent_pseudo_moves() {
+        let mut game = ChessGame::new();
+        assert_eq!(game.opponent_pseudo_moves().len(), 20);
+        game.make_move(game.parse_san("e4").unwrap()).unwrap();
+        let moves = game.opponent_pseudo_moves();
+        assert!(moves.iter().all(|m| m.piece.get_color() == White));
+        // the bishop and queen can come out now
+        assert_eq!(moves.len(), 30);
+        assert_eq!(game.side_to_move, Black);
+    }
+
+    #[test]
+    fn test_legal_moves_sorted() {
+        let game = ChessGame::new();
+        let uci: Vec<String> = game
+            .legal_moves_sorted()
+            .iter()
+            .map(|m| m.to_uci())
+            .collect();
+        assert_eq!(uci[..4], ["b1a3", "b1c3", "g1f3", "g1h3"]);
+        assert_eq!(uci[4..6], ["a2a3", "a2a4"]);
+        let promotion = ChessGame::new_position("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let uci: Vec<String> = promotion
+            .legal_moves_sorted()
+            .iter()
+            .map(|m| m.to_uci())
+            .collect();
+        assert_eq!(uci[uci.len() - 4..], ["a7a8n", "a7a8b", "a7a8r", "a7a8q"]);
+    }
+
+    #[test]
+    fn test_move_between() {
+        let c = |n: &str| -> Coord { n.parse().unwrap() };
+        let game = ChessGame::new_position("r3k3/1P6/8/3pP3/8/8/8/R3K2R w KQq d6 0 1").unwrap();
+        let ep = game.move_between(c("e5"), c("d6"), None).unwrap();
+        assert!(ep.enpassant && ep.capture);
+        let castle = game.move_between(c("e1"), c("g1"), None).unwrap();
+        assert!(castle.castling);
+        assert!(game.move_between(c("b7"), c("a8"), None).is_err());
+        let promotion = game
+            .move_between(c("b7"), c("a8"), Some(Piece::Rook(White)))
+            .unwrap();
+        assert!(promotion.capture);
+        assert_eq!(promotion.to_uci(), "b7a8r");
+        assert!(game.move_between(c("e1"), c("e3"), None).is_err());
+        assert!(game
+            .move_between(c("a1"), c("a2"), Some(Piece::Queen(White)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_promotion_flow() {
+        let game = ChessGame::new_position("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a7 = "a7".parse().unwrap();
+        let (a8, b8) = ("a8".parse().unwrap(), "b8".parse().unwrap());
+        assert!(game.pending_promotion(a7, a8));
+        assert!(game.pending_promotion(a7, b8));
+        assert!(!game.pending_promotion("e1".parse().unwrap(), "e2".parse().unwrap()));
+        let m = game
+            .complete_promotion(a7, b8, Piece::Knight(Black))
+            .unwrap();
+        assert_eq!(m.to_uci(), "a7b8n");
+        assert!(m.capture);
+        assert!(game.complete_promotion(a7, b8, Piece::King(White)).is_err());
+        assert!(game
+            .complete_promotion(a7, "a6".parse().unwrap(), Piece::Queen(White))
+            .is_err());
+    }
+
+    #[test]
+    fn test_legal_targets() {
+        let game = ChessGame::new();
+        assert_eq!(
+            game.legal_targets("g1".parse().unwrap()),
+            coords(&["h3", "f3"])
+        );
+        assert_eq!(
+            game.legal_targets("e2".parse().unwrap()),
+            coords(&["e3", "e4"])
+        );
+        // not the side to move, and empty squares
+        assert!(game.legal_targets("e7".parse().unwrap()).is_empty());
+        assert!(game.legal_targets("e4".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_attacks_from_as() {
+        let game = ChessGame::new();
+        let knight = Piece::Knight(Color::White);
+        assert_eq!(
+            game.attacks_from_as("d5".parse().unwrap(), knight),
+            coords(&["c3", "e3", "b4", "f4", "b6", "f6", "c7", "e7"])
+        );
+        assert_eq!(
+            game.attacks_from_as("a1".parse().unwrap(), knight),
+            coords(&["c2", "b3"])
+        );
+        // a rook on the empty e4 is stopped by the pawns on e2 and e7
+        let rook = game.attacks_from_as("e4".parse().unwrap(), Piece::Rook(Color::Black));
+        assert_eq!(rook.len(), 12);
+        assert!(rook.contains(&"e2".parse().unwrap()));
+        assert!(!rook.contains(&"e1".parse().unwrap()));
+        assert_eq!(
+            game.attacks_from_as("e4".parse().unwrap(), Piece::Pawn(Color::Black)),
+            coords(&["d3", "f3"])
+        );
+    }
+
+    #[test]
+    fn test_capture_target() {
+        // after 1. e4 d5 2. e5 f5
+        let game = ChessGame::new_position(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        )
+        .unwrap();
+        let en_passant = game.parse_san("exf6").unwrap();
+        assert_eq!(
+            game.capture_target(en_passant),
+            Some(("f5".parse().unwrap(), Piece::Pawn(Color::Black)))
+        );
+        let game =
+            ChessGame::new_position("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
+                .unwrap();
+        let capture = game.parse_san("exd5").unwrap();
+        assert_eq!(
+            game.capture_target(capture),
+            Some(("d5".parse().unwrap(), Piece::Pawn(Color::Black)))
+        );
+        assert_eq!(game.capture_target(game.parse_san("e5").unwrap()), None);
+    }
+
+    #[test]
+    fn test_double_push_rules() {
+        let double_pushes = |fen: &str| {
+            let game = ChessGame::new_position(fen).unwrap();
+            game.legal_moves()
+                .into_iter()
+                .filter(|m| m.double_push)
+                .map(|m| m.to_uci())
+                .collect::<Vec<String>>()
+        };
+        // only from the start rank of each color
+        assert_eq!(
+            double_pushes("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1"),
+            Vec::<String>::new()
+        );
+        assert_eq!(double_pushes("4k3/4p3/3p4/8/8/8/8/4K3 b - - 0 1"), ["e7e5"]);
+        assert!(double_pushes("4k3/4P3/8/8/8/8/4p3/4K3 w - - 0 1").is_empty());
+        // a piece on either square stops it, but not the single push
+        let blocked_target = ChessGame::new_position("4k3/8/8/8/4n3/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            blocked_target.legal_targets("e2".parse().unwrap()),
+            coords(&["e3"])
+        );
+        assert!(double_pushes("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1").is_empty());
+        assert!(double_pushes("4k3/4p3/4N3/8/8/8/8/4K3 b - - 0 1").is_empty());
+    }
+
+    fn mask(names: &[&str]) -> u64 {
+        names
+            .iter()
+            .fold(0, |bits, n| bits | 1 << name_to_tile(n).unwrap())
+    }
+
+    #[test]
+    fn test_sliding_attacks() {
+        let d4: Coord = "d4".parse().unwrap();
+        let blockers = mask(&["d6", "f4", "b2"]);
+        assert_eq!(
+            rook_attacks(d4, blockers),
+            mask(&["d5", "d6", "d3", "d2", "d1", "c4", "b4", "a4", "e4", "f4"])
+        );
+        assert_eq!(
+            bishop_attacks(d4, blockers),
+            mask(&["e5", "f6", "g7", "h8", "e3", "f2", "g1", "c3", "b2", "c5", "b6", "a7"])
+        );
+        assert_eq!(
+            queen_attacks(d4, blockers),
+            rook_attacks(d4, blockers) | bishop_attacks(d4, blockers)
+        );
+        assert_eq!(rook_attacks(d4, 0).count_ones(), 14);
+
+        // the generator agrees on the sliders of a busy position
+        let game = ChessGame::new_position(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let occupied = occupancy(&game.board);
+        let own = (0..64)
+            .filter(|t| game.board[*t].is_some_and(|p| p.get_color() == Color::White))
+            .fold(0u64, |bits, t| bits | 1 << t);
+        for (square, attacks) in [
+            ("f3", queen_attacks as fn(Coord, u64) -> u64),
+            ("e2", bishop_attacks),
+        ] {
+            let from: Coord = square.parse().unwrap();
+            let generated = game
+                .pseudo_legal_moves()
+                .iter()
+                .filter(|m| m.source == from)
+                .fold(0u64, |bits, m| bits | 1 << m.target.to_usize());
+            assert_eq!(generated, attacks(from, occupied) & !own, "{}", square);
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_for_piece_type() {
+        let game = ChessGame::new();
+        assert_eq!(
+            game.legal_moves_for_piece_type(Piece::Knight(Color::Black))
+                .len(),
+            4
+        );
+        assert!(game
+            .legal_moves_for_piece_type(Piece::Queen(Color::White))
+            .is_empty());
+        // castling comes with the king's moves
+        let game = ChessGame::new_position("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let king_moves = game.legal_moves_for_piece_type(Piece::King(Color::White));
+        assert_eq!(king_moves.iter().filter(|m| m.castling).count(), 2);
+        assert!(king_moves
+            .iter()
+            .all(|m| m.piece == Piece::King(Color::White)));
+    }
+
+    #[test]
+    fn test_space() {
+        let game = ChessGame::new();
+        assert_eq!((game.space(Color::White), game.space(Color::Black)), (0, 0));
+        // the pawns on d4 and e5 claim c5, e5 and d6, while f6 is covered by the pawn on g7
+        let game = ChessGame::new_position(
+            "rnbqkbnr/pp3ppp/4p3/2ppP3/3P4/8/PPP2PPP/RNBQKBNR w KQkq - 0 4",
+        )
+        .unwrap();
+        assert_eq!(game.space(Color::White), 3);
+        assert!(game.space(Color::White) > ChessGame::new().space(Color::White));
+    }
+
+    #[test]
+    fn test_mobility() {
+        let game = ChessGame::new();
+        assert_eq!(game.mobility(Color::White), 20);
+        assert_eq!(game.mobility(Color::Black), 20);
+        assert_eq!(game.mobility_by_piece(Color::White), [16, 4, 0, 0, 0, 0]);
+        // Black's pieces aren't pinned when it's White's turn, so pseudo-legal moves count
+        let game = ChessGame::new_position("4k3/4n3/8/8/8/8/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(game.mobility_by_piece(Color::Black)[1], 6);
+        assert_eq!(game.mobility(Color::White), 14);
+    }
+
+    #[test]
+    fn test_highlight_check() {
+        let mut board = Board::new();
+        board.clear_selections();
+        ChessGame::new().highlight_check(&mut board);
+        assert!(board.selections.is_empty());
+
+        let game = ChessGame::new_position(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        )
+        .unwrap();
+        game.highlight_check(&mut board);
+        assert_eq!(board.selections.len(), 1);
+        assert_eq!(
+            board.selections[0].squares(),
+            [name_to_tile("e1").unwrap(), name_to_tile("h4").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_can_select() {
+        let mut game = ChessGame::new();
+        assert!(game.can_select("e2".parse().unwrap()));
+        assert!(game.can_select("a1".parse().unwrap()));
+        assert!(!game.can_select("e7".parse().unwrap()));
+        assert!(!game.can_select("e4".parse().unwrap()));
+        game.make_move(game.parse_san("e4").unwrap()).unwrap();
+        assert!(game.can_select("e7".parse().unwrap()));
+        assert!(!game.can_select("e4".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_highlight_legal_targets() {
+        let game = ChessGame::new_position(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        )
+        .unwrap();
+        let green = SelectionColor::new(0, 200, 0);
+        let mut board = game.board.clone();
+        board.clear_selections();
+        game.highlight_legal_targets("e5".parse().unwrap(), &mut board, green);
+        assert_eq!(board.selections.len(), 2);
+        assert_eq!(board.selections[0].squares(), [name_to_tile("e6").unwrap()]);
+        assert_eq!(board.selections[0].color(), green);
+        // the en passant capture counts as a capture
+        assert_eq!(board.selections[1].squares(), [name_to_tile("f6").unwrap()]);
+        assert_eq!(board.selections[1].color(), green.darker());
+
+        board.clear_selections();
+        game.highlight_legal_targets("e8".parse().unwrap(), &mut board, green);
+        assert!(board.selections.is_empty());
+    }
+
+    #[test]
+    fn test_legal_targets_pinned_piece() {
+        // the knight on e2 is pinned by the rook on e8
+        let game = ChessGame::new_position("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(game.legal_targets("e2".parse().unwrap()).is_empty());
+    }
+}
@@ -13,6 +13,8 @@ pub struct Move {
     pub double_push: bool,
     pub enpassant: bool,
     pub castling: bool,
+    /// Where the rook starts, for castling moves.
+    pub castling_rook: Option<Coord>,
 }
 
 impl Move {
@@ -26,8 +28,18 @@ impl Move {
             castling: false,
             double_push: false,
             enpassant: false,
+            castling_rook: None,
         }
     }
+    /// Same as `new` for code working with tiles, without a promotion and with all flags unset.
+    pub fn from_tiles(source: Tile, target: Tile, piece: Piece) -> Self {
+        Self::new(
+            Coord::from_tile(source),
+            Coord::from_tile(target),
+            piece,
+            None,
+        )
+    }
     pub fn capture(&mut self, capture: bool) -> Self {
         self.capture = capture;
         *self
@@ -45,23 +57,17 @@ impl Move {
         *self
     }
     // specific intialitzers for comfort
+    /// A pawn moving two squares forward. Neither the start rank nor the squares in between are
+    /// checked; the move generator only builds these when they're free.
     pub fn new_pawn_double_push(color: Color, source: Coord) -> Self {
         Self::new(
             source,
             //TODO: this is now saturating, just in case. Find a better fix
-            if color == Color::White {
-                source
-                    .next_up()
-                    .unwrap_or(source)
-                    .next_up()
-                    .unwrap_or(source)
-            } else {
-                source
-                    .next_down()
-                    .unwrap_or(source)
-                    .next_down()
-                    .unwrap_or(source)
-            },
+            source
+                .offset(0, color.pawn_direction())
+                .unwrap_or(source)
+                .offset(0, color.pawn_direction())
+                .unwrap_or(source),
             Pawn(color),
             None,
         )
@@ -75,11 +81,7 @@ impl Move {
         Self::new(
             source,
             //TODO: this is now saturating, just in case. Find a better fix
-            if color == Color::White {
-                source.next_up().unwrap_or(source)
-            } else {
-                source.next_down().unwrap_or(source)
-            },
+            source.offset(0, color.pawn_direction()).unwrap_or(source),
             Pawn(color),
             None,
         )
@@ -93,11 +95,7 @@ impl Move {
         Self::new(
             source,
             //TODO: this is now saturating, just in case. Find a better fix
-            if color == Color::White {
-                source.next_up().unwrap_or(source)
-            } else {
-                source.next_down().unwrap_or(source)
-            },
+            source.offset(0, color.pawn_direction()).unwrap_or(source),
             Pawn(color),
             Some(piece),
         )
@@ -131,16 +129,54 @@ impl Move {
             .castling(false)
             .enpassant(false)
     }
-    pub fn new_castling(source: Coord, target: Coord, color: Color) -> Self {
-        Move::new(source, target, King(color), None)
+    /// The king castling from `source` to `target` with the rook on `rook`.
+    pub fn new_castling(source: Coord, target: Coord, rook: Coord, color: Color) -> Self {
+        let mut m = Move::new(source, target, King(color), None)
             .capture(false)
             .double_push(false)
             .castling(true)
-            .enpassant(false)
+            .enpassant(false);
+        m.castling_rook = Some(rook);
+        m
     }
     pub fn set_promotion(&mut self, prom: Option<Piece>) {
         self.promoted_piece = prom;
     }
+    /// Packs the move in 16 bits: source tile in bits 0-5, target tile in bits 6-11 and the
+    /// promotion or flag in bits 12-15 (0 none, 1 knight, 2 bishop, 3 rook, 4 queen, 5 castling).
+    /// Castling needs its flag because in Chess960 the king may castle to a square it could also
+    /// step to. The rest of the fields can be recovered from the position with
+    /// `ChessGame::move_from_packed`.
+    pub fn to_packed(&self) -> u16 {
+        let promotion = match self.promoted_piece {
+            None if self.castling => 5,
+            None => 0,
+            Some(Knight(_)) => 1,
+            Some(Bishop(_)) => 2,
+            Some(Rook(_)) => 3,
+            Some(_) => 4,
+        };
+        self.source.to_usize() as u16 | (self.target.to_usize() as u16) << 6 | promotion << 12
+    }
+    /// The move in UCI long algebraic notation, like `e2e4` or `e7e8q`. Castling is written as
+    /// the king moving two files when the king and rooks start on their usual squares, and as
+    /// the king taking its rook otherwise, as Chess960 engines expect (`b1a1`).
+    pub fn to_uci(&self) -> String {
+        if let Some(rook) = self.castling_rook.filter(|_| self.castling) {
+            if self.source.file() != 4 || (rook.file() != 0 && rook.file() != 7) {
+                return format!("{}{}", self.source, rook);
+            }
+        }
+        match self.promoted_piece {
+            Some(p) => format!(
+                "{}{}{}",
+                self.source,
+                self.target,
+                p.get_letter().to_ascii_lowercase()
+            ),
+            None => format!("{}{}", self.source, self.target),
+        }
+    }
 }
 
 pub fn print_movelist(movelist: &[Move]) {
@@ -154,14 +190,14 @@ use core::fmt::{Display, Formatter, Result};
 
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        if self.promoted_piece.is_some() {
+        if let Some(promoted_piece) = self.promoted_piece {
             write!(
                 f,
                 "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
                 self.source,
                 self.target,
                 self.piece,
-                self.promoted_piece.unwrap(),
+                promoted_piece,
                 self.capture,
                 self.double_push,
                 self.enpassant,
@@ -183,6 +219,33 @@ impl Display for Move {
     }
 }
 
+/// Moves are ordered by source tile, then target tile (a1 = 0, h8 = 63), then promotion piece
+/// (none, knight, bishop, rook, queen), with castling after a king step to the same square. The
+/// remaining fields break ties, so two moves only compare equal when they are equal, as sets and
+/// maps of moves gathered from several positions need.
+impl Ord for Move {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let key = |m: &Move| {
+            (
+                m.source.to_usize(),
+                m.target.to_usize(),
+                m.to_packed() >> 12,
+                m.piece,
+                m.promoted_piece,
+                (m.capture, m.double_push, m.enpassant, m.castling),
+                m.castling_rook.map(|c| c.to_usize()),
+            )
+        };
+        key(self).cmp(&key(other))
+    }
+}
+
+impl PartialOrd for Move {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // MOVE RECORD
 //
 
@@ -191,3 +254,67 @@ pub struct MoveRecord {
     pub name: String,
     pub count: u128,
 }
+
+impl Display for MoveRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}: {}", self.name, self.count)
+    }
+}
+
+// MOVE HISTORY
+//
+
+/// A played move together with the state needed to describe or revert it.
+#[derive(Debug, Copy, Clone)]
+pub struct HistoryEntry {
+    pub mv: Move,
+    pub captured: Option<Piece>,
+    pub castling_rights: crate::castling::CastlingRights,
+    pub enpassant_target_square: Option<Coord>,
+    pub halfmove_clock: u32,
+    /// Zobrist hash of the position before the move.
+    pub hash: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_move_ordering() {
+        let c = |n: &str| -> Coord { n.parse().unwrap() };
+        let a2a3 = Move::new_pawn_push(Color::White, c("a2"));
+        let a2a4 = Move::new_pawn_double_push(Color::White, c("a2"));
+        let knight = Move::new_knight_move(c("b1"), c("c3"), Color::White, false);
+        let queen = Move::new_promotion(Color::White, c("a7"), Queen(Color::White));
+        let rook = Move::new_promotion(Color::White, c("a7"), Rook(Color::White));
+        let set: BTreeSet<Move> = [queen, a2a4, knight, rook, a2a3, a2a4]
+            .into_iter()
+            .collect();
+        let ordered: Vec<Move> = set.into_iter().collect();
+        assert_eq!(ordered, [knight, a2a3, a2a4, rook, queen]);
+        // Chess960 castles from different positions with the king going e1 to g1
+        let rook_f1 = Move::new_castling(c("e1"), c("g1"), c("f1"), Color::White);
+        let rook_h1 = Move::new_castling(c("e1"), c("g1"), c("h1"), Color::White);
+        assert_ne!(rook_f1.cmp(&rook_h1), std::cmp::Ordering::Equal);
+        let castles: BTreeSet<Move> = [rook_f1, rook_h1].into_iter().collect();
+        assert_eq!(castles.len(), 2);
+    }
+
+    #[test]
+    fn test_from_tiles() {
+        let m = Move::from_tiles(1, 18, Knight(Color::White));
+        assert_eq!(
+            m,
+            Move::new_knight_move(
+                Coord::from_tile(1),
+                Coord::from_tile(18),
+                Color::White,
+                false
+            )
+        );
+        assert_eq!(m.to_uci(), "b1c3");
+        assert!(!m.capture && !m.double_push && !m.enpassant && !m.castling);
+    }
+}
@@ -0,0 +1,247 @@
+// Performance test (perft): counts the leaf nodes of the legal move tree up to a given depth.
+// The counts are well known for many positions, which makes this the main correctness check for
+// the move generator.
+
+use crate::moves::{Move, MoveRecord};
+use crate::ChessGame;
+
+impl ChessGame {
+    /// Counts the positions reachable in exactly `depth` plies.
+    pub fn perft(&self, depth: u32) -> u128 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u128;
+        }
+        moves
+            .into_iter()
+            .map(|m| {
+                let mut child = self.clone();
+                // legal moves always have a piece on the source square
+                child.make_move(m).unwrap();
+                child.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    /// Perft count for every root move, named in UCI notation.
+    pub fn perft_divide(&self, depth: u32) -> Vec<MoveRecord> {
+        self.legal_moves()
+            .into_iter()
+            .map(|m| {
+                let mut child = self.clone();
+                child.make_move(m).unwrap();
+                MoveRecord {
+                    name: m.to_uci(),
+                    count: child.perft(depth.saturating_sub(1)),
+                }
+            })
+            .collect()
+    }
+
+    /// Same as `perft`, but the root moves are split across threads. Needs the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn perft_parallel(&self, depth: u32) -> u128 {
+        use rayon::prelude::*;
+
+        if depth <= 1 {
+            return self.perft(depth);
+        }
+        self.legal_moves()
+            .into_par_iter()
+            .map(|m| {
+                let mut child = self.clone();
+                child.make_move(m).unwrap();
+                child.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    /// Visits the legal move tree depth first, calling `visit` on every leaf with the moves that
+    /// lead to it. Leaves are the positions exactly `depth` plies away, like the ones `perft`
+    /// counts; lines ending earlier in mate or stalemate aren't visited.
+    pub fn walk<F: FnMut(&ChessGame, &[Move])>(&self, depth: u32, mut visit: F) {
+        let depth_reached = depth as usize;
+        self.walk_tree(
+            depth,
+            |game, path| {
+                if path.len() == depth_reached {
+                    visit(game, path)
+                }
+            },
+            |_, _| {},
+        );
+    }
+
+    /// Visits every node of the legal move tree up to `depth` plies, calling `pre` before
+    /// descending into a node's children and `post` after, like `walk` does for the leaves.
+    pub fn walk_tree<Pre, Post>(&self, depth: u32, mut pre: Pre, mut post: Post)
+    where
+        Pre: FnMut(&ChessGame, &[Move]),
+        Post: FnMut(&ChessGame, &[Move]),
+    {
+        let mut game = self.clone();
+        game.walk_node(depth, &mut vec![], &mut pre, &mut post);
+    }
+
+    fn walk_node<Pre, Post>(
+        &mut self,
+        depth: u32,
+        path: &mut Vec<Move>,
+        pre: &mut Pre,
+        post: &mut Post,
+    ) where
+        Pre: FnMut(&ChessGame, &[Move]),
+        Post: FnMut(&ChessGame, &[Move]),
+    {
+        pre(self, path);
+        if depth > 0 {
+            for m in self.legal_moves() {
+                self.make_move(m).unwrap();
+                path.push(m);
+                self.walk_node(depth - 1, path, pre, post);
+                path.pop();
+                self.unmake_move();
+            }
+        }
+        post(self, path);
+    }
+}
+
+/// Lays out a `perft_divide` result like Stockfish's `go perft`: one `move: count` line per root
+/// move and the total number of nodes at the bottom.
+pub fn format_perft_divide(records: &[MoveRecord]) -> String {
+    let mut out = String::new();
+    for r in records {
+        out.push_str(&format!("{}\n", r));
+    }
+    let total: u128 = records.iter().map(|r| r.count).sum();
+    out.push_str(&format!("\nNodes searched: {}\n", total));
+    out
+}
+
+pub fn print_perft_divide(records: &[MoveRecord]) {
+    print!("{}", format_perft_divide(records));
+}
+
+/// Checks the legal moves against a slow reference and panics if they differ. `legal_moves`
+/// judges most moves with the checks and pins of the position; the reference plays every
+/// pseudo-legal move on a copy of the board instead. A second generator, such as a bitboard one,
+/// should be added to the comparison here.
+#[cfg(test)]
+pub(crate) fn assert_generators_agree(game: &ChessGame) {
+    let mut fast = game.legal_moves_sorted();
+    let mut reference: Vec<Move> = game
+        .pseudo_legal_moves()
+        .into_iter()
+        .filter(|m| game.is_king_safe_after(m))
+        .collect();
+    reference.sort();
+    if fast != reference {
+        fast.retain(|m| !reference.contains(m));
+        let extra: Vec<String> = fast.iter().map(|m| m.to_uci()).collect();
+        let legal = game.legal_moves();
+        let missing: Vec<String> = reference
+            .iter()
+            .filter(|m| !legal.contains(m))
+            .map(|m| m.to_uci())
+            .collect();
+        panic!(
+            "generators disagree in {}: extra {:?}, missing {:?}",
+            game.to_fen(),
+            extra,
+            missing
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::perft::{assert_generators_agree, format_perft_divide};
+    use crate::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn test_perft_start_position() {
+        let game = ChessGame::new();
+        assert_eq!(game.perft(1), 20);
+        assert_eq!(game.perft(2), 400);
+        assert_eq!(game.perft(3), 8902);
+        game.walk_tree(2, |node, _| assert_generators_agree(node), |_, _| ());
+    }
+
+    #[test]
+    fn test_perft_positions() {
+        let positions = [
+            (KIWIPETE, 2, 2039),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+                9467,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                2,
+                1486,
+            ),
+        ];
+        for (fen, depth, nodes) in positions {
+            let game = ChessGame::new_position(fen).unwrap();
+            assert_eq!(game.perft(depth), nodes, "{}", fen);
+            // every node whose moves were counted
+            game.walk_tree(
+                depth - 1,
+                |node, _| assert_generators_agree(node),
+                |_, _| (),
+            );
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let game = ChessGame::new_position(KIWIPETE).unwrap();
+        let mut leaves = 0;
+        game.walk(2, |leaf, path| {
+            assert_eq!(path.len(), 2);
+            assert_eq!(leaf.history.len(), 2);
+            leaves += 1;
+        });
+        assert_eq!(leaves, game.perft(2));
+
+        let mut entered = 0;
+        let mut left = 0;
+        ChessGame::new().walk_tree(2, |_, _| entered += 1, |_, _| left += 1);
+        assert_eq!((entered, left), (1 + 20 + 400, 421));
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let divide = ChessGame::new().perft_divide(2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|r| r.count == 20));
+        assert!(divide.iter().any(|r| r.name == "g1f3"));
+        assert_eq!(divide[0].to_string(), format!("{}: 20", divide[0].name));
+        assert!(format_perft_divide(&divide).ends_with("\nNodes searched: 400\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_perft_parallel() {
+        let game = ChessGame::new_position(KIWIPETE).unwrap();
+        assert_eq!(game.perft_parallel(3), game.perft(3));
+        let endgame = ChessGame::new_position("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(endgame.perft_parallel(4), 43238);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    #[ignore = "slow, run with --release --ignored"]
+    fn test_perft_parallel_kiwipete_depth_5() {
+        let game = ChessGame::new_position(KIWIPETE).unwrap();
+        assert_eq!(game.perft_parallel(5), 193690690);
+    }
+}
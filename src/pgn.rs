@@ -0,0 +1,509 @@
+// Portable Game Notation (PGN) import.
+//
+// A PGN game is a list of tag pairs followed by the movetext. Comments, numeric annotation glyphs
+// (NAGs) and `[%eval]` engine evaluations are kept with the move they follow, variations are
+// skipped, and every move is resolved against the position so the game is fully validated while
+// parsing.
+
+use anyhow::*;
+
+use crate::clock::TimeControl;
+use crate::color::Color::White;
+use crate::moves::Move;
+use crate::ChessGame;
+
+/// A move with the annotations PGN can attach to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotatedMove {
+    pub mv: Move,
+    /// Numeric annotation glyphs, like 1 for `!` or 4 for `??`, in the order they were given.
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// Engine evaluation in centipawns, from White's point of view.
+    pub eval: Option<i32>,
+}
+
+impl AnnotatedMove {
+    pub fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            nags: vec![],
+            comment: None,
+            eval: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<AnnotatedMove>,
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Returns the value of a tag, like `tag("White")`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The time control from the `TimeControl` tag, if the game has a known one.
+    pub fn time_control(&self) -> Result<Option<TimeControl>, anyhow::Error> {
+        match self.tag("TimeControl") {
+            Some(tag) => TimeControl::from_pgn_tag(tag),
+            None => Ok(None),
+        }
+    }
+
+    /// The position the game starts from: the `FEN` tag if there is one, or the standard start.
+    pub fn initial_position(&self) -> Result<ChessGame, anyhow::Error> {
+        match self.tag("FEN") {
+            Some(fen) => ChessGame::new_position(fen),
+            None => Ok(ChessGame::new()),
+        }
+    }
+}
+
+/// The number written before a move played from `position`: `14.` for White, and `14...` for
+/// Black when the move doesn't follow White's, like at the start or after a comment.
+fn move_number(position: &ChessGame, after_break: bool) -> Option<String> {
+    if position.side_to_move == White {
+        Some(format!("{}.", position.fullmove_clock))
+    } else if after_break {
+        Some(format!("{}...", position.fullmove_clock))
+    } else {
+        None
+    }
+}
+
+impl ChessGame {
+    /// The moves played so far in numbered SAN, like `14... Nf6 15. Bb5`. The numbering follows
+    /// the position the game started from, which may be a mid-game FEN.
+    pub fn san_move_list(&self) -> String {
+        let mut position = self.clone();
+        while position.unmake_move().is_some() {}
+        let mut words = vec![];
+        for entry in &self.history {
+            words.extend(move_number(&position, words.is_empty()));
+            words.push(position.move_to_san(entry.mv));
+            // moves in the history were legal when played
+            position.make_move(entry.mv).unwrap();
+        }
+        words.join(" ")
+    }
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+fn parse_tag(line: &str) -> Result<(String, String), anyhow::Error> {
+    let inner = line
+        .trim()
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(|| anyhow!("malformed tag {}", line))?;
+    let (name, value) = inner
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("malformed tag {}", line))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| anyhow!("malformed tag value {}", line))?;
+    Ok((name.to_string(), unescape_tag_value(value)))
+}
+
+/// Undoes the escaping of `"` and `\` in a tag value.
+fn unescape_tag_value(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// the move suffixes with a standard NAG
+const GLYPHS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+enum Token {
+    San(String),
+    Nag(u8),
+    Comment(String),
+}
+
+/// Splits the movetext into moves, NAGs and comments, and the result, dropping move numbers and
+/// variations.
+fn movetext_tokens(movetext: &str) -> Result<(Vec<Token>, Option<String>), anyhow::Error> {
+    let mut words = vec![];
+    let mut result = None;
+    let mut chars = movetext.chars().peekable();
+    let mut variation_depth = 0;
+    let mut current = String::new();
+    let flush = |words: &mut Vec<Token>, current: &mut String, depth: i32| {
+        if depth == 0 && !current.is_empty() {
+            words.push(Token::San(std::mem::take(current)));
+        }
+        current.clear();
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                flush(&mut words, &mut current, variation_depth);
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(anyhow!("unterminated comment")),
+                    }
+                }
+                if variation_depth == 0 {
+                    words.push(Token::Comment(comment.trim().to_string()));
+                }
+            }
+            ';' => {
+                flush(&mut words, &mut current, variation_depth);
+                let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                if variation_depth == 0 {
+                    words.push(Token::Comment(comment.trim().to_string()));
+                }
+            }
+            '(' => {
+                flush(&mut words, &mut current, variation_depth);
+                variation_depth += 1;
+            }
+            ')' => {
+                flush(&mut words, &mut current, variation_depth);
+                variation_depth -= 1;
+                if variation_depth < 0 {
+                    return Err(anyhow!("unbalanced variation"));
+                }
+            }
+            c if c.is_whitespace() => flush(&mut words, &mut current, variation_depth),
+            c => current.push(c),
+        }
+    }
+    flush(&mut words, &mut current, variation_depth);
+    if variation_depth != 0 {
+        return Err(anyhow!("unterminated variation"));
+    }
+
+    let mut tokens = vec![];
+    for word in words {
+        let word = match word {
+            Token::San(word) => word,
+            other => {
+                tokens.push(other);
+                continue;
+            }
+        };
+        if RESULTS.contains(&word.as_str()) {
+            result = Some(word);
+            continue;
+        }
+        if let Some(nag) = word.strip_prefix('$') {
+            let nag = nag
+                .parse()
+                .map_err(|_| anyhow!("invalid annotation glyph {}", word))?;
+            tokens.push(Token::Nag(nag));
+            continue;
+        }
+        // move numbers, possibly glued to the move like "1.e4" or "12...Nf6"
+        let san = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        // suffix glyphs like "Nf6?!", or on their own after the move
+        let glyph_start = san.trim_end_matches(['!', '?']).len();
+        let (san, glyph) = san.split_at(glyph_start);
+        if !san.is_empty() {
+            tokens.push(Token::San(san.to_string()));
+        }
+        if let Some((_, nag)) = GLYPHS.iter().find(|(g, _)| *g == glyph) {
+            tokens.push(Token::Nag(*nag));
+        }
+    }
+    Ok((tokens, result))
+}
+
+/// Splits an `[%eval 0.35]` command off a comment, returning the evaluation in centipawns and
+/// the rest of the text. Mate scores (`#3`) are left in the comment.
+fn extract_eval(comment: &str) -> (Option<i32>, Option<String>) {
+    let parsed = comment.find("[%eval ").and_then(|start| {
+        let end = start + comment[start..].find(']')?;
+        let pawns: f64 = comment[start + 7..end].trim().parse().ok()?;
+        let rest = format!("{} {}", &comment[..start], &comment[end + 1..]);
+        Some(((pawns * 100.0).round() as i32, rest.trim().to_string()))
+    });
+    match parsed {
+        Some((eval, rest)) => (Some(eval), Some(rest).filter(|r| !r.is_empty())),
+        None => (None, Some(comment.to_string()).filter(|c| !c.is_empty())),
+    }
+}
+
+/// Parses a single PGN game.
+pub fn parse_pgn(text: &str) -> Result<PgnGame, anyhow::Error> {
+    let mut tags = vec![];
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && movetext.trim().is_empty() {
+            tags.push(parse_tag(trimmed)?);
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    let (tokens, result) = movetext_tokens(&movetext)?;
+
+    let mut game = PgnGame {
+        tags,
+        moves: vec![],
+        result: String::new(),
+    };
+    let mut position = game.initial_position()?;
+    for token in tokens {
+        match token {
+            Token::San(san) => {
+                let ply = game.moves.len() + 1;
+                let m = position
+                    .parse_san(&san)
+                    .map_err(|e| anyhow!("ply {}: {}", ply, e))?;
+                position.make_move(m)?;
+                game.moves.push(AnnotatedMove::new(m));
+            }
+            // annotations before the first move have nothing to attach to
+            Token::Nag(nag) => {
+                if let Some(last) = game.moves.last_mut() {
+                    last.nags.push(nag);
+                }
+            }
+            Token::Comment(comment) => {
+                if let Some(last) = game.moves.last_mut() {
+                    let (eval, text) = extract_eval(&comment);
+                    last.eval = eval.or(last.eval);
+                    last.comment = match (last.comment.take(), text) {
+                        (Some(a), Some(b)) => Some(format!("{} {}", a, b)),
+                        (a, b) => a.or(b),
+                    };
+                }
+            }
+        }
+    }
+    game.result = result
+        .or_else(|| game.tag("Result").map(str::to_string))
+        .unwrap_or_else(|| "*".to_string());
+    Ok(game)
+}
+
+impl PgnGame {
+    /// Writes the game as PGN, with its tags and the annotations of every move. Lines are
+    /// wrapped at 80 characters.
+    pub fn to_pgn(&self) -> Result<String, anyhow::Error> {
+        let mut out = String::new();
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        out.push('\n');
+
+        let mut words = vec![];
+        let mut position = self.initial_position()?;
+        let mut needs_number = true;
+        for annotated in &self.moves {
+            words.extend(move_number(&position, needs_number));
+            words.push(position.move_to_san(annotated.mv));
+            needs_number = false;
+            for nag in &annotated.nags {
+                words.push(format!("${}", nag));
+            }
+            if annotated.eval.is_some() || annotated.comment.is_some() {
+                let mut comment = vec![];
+                if let Some(eval) = annotated.eval {
+                    comment.push(format!("[%eval {:.2}]", eval as f64 / 100.0));
+                }
+                comment.extend(annotated.comment.clone());
+                words.push(format!("{{{}}}", comment.join(" ")));
+                needs_number = true;
+            }
+            position.make_move(annotated.mv)?;
+        }
+        words.push(self.result.clone());
+
+        let mut line = String::new();
+        for word in words {
+            if !line.is_empty() && line.len() + 1 + word.len() > 80 {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        out.push_str(&line);
+        out.push('\n');
+        Ok(out)
+    }
+}
+
+/// Parses a file with many games. Each game is parsed on its own, so a malformed game is
+/// reported in its slot without stopping the rest of the import.
+pub fn parse_pgn_collection(text: &str) -> Vec<Result<PgnGame, anyhow::Error>> {
+    let mut games = vec![];
+    let mut current = String::new();
+    let mut in_movetext = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        // a tag after some movetext starts the next game
+        if trimmed.starts_with('[') && in_movetext {
+            games.push(std::mem::take(&mut current));
+            in_movetext = false;
+        } else if !trimmed.is_empty() && !trimmed.starts_with('[') {
+            in_movetext = true;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        games.push(current);
+    }
+    games.iter().map(|g| parse_pgn(g)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pgn::*;
+
+    const COLLECTION: &str = r#"[Event "Casual"]
+[TimeControl "180+2"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 {threatening mate} Nf6?? (3... g6) 4. Qxf7# 1-0
+
+[Event "Broken"]
+[Result "*"]
+
+1. e4 e5 2. Ke3 *
+
+[Event "Short"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+
+1. O-O $1 Kd7 1/2-1/2
+"#;
+
+    #[test]
+    fn test_parse_pgn_collection() {
+        let games = parse_pgn_collection(COLLECTION);
+        assert_eq!(games.len(), 3);
+
+        let first = games[0].as_ref().unwrap();
+        assert_eq!(first.tag("White"), Some("Alice"));
+        assert_eq!(first.moves.len(), 7);
+        assert_eq!(first.result, "1-0");
+        assert_eq!(first.moves[6].mv.to_uci(), "h5f7");
+        assert_eq!(first.moves[4].comment.as_deref(), Some("threatening mate"));
+        assert_eq!(first.moves[5].nags, [4]);
+        let tc = first.time_control().unwrap().unwrap();
+        assert_eq!(tc.periods[0].base_ms, 180_000);
+
+        let error = games[1].as_ref().unwrap_err().to_string();
+        assert!(error.contains("ply 3"), "{}", error);
+
+        let third = games[2].as_ref().unwrap();
+        assert!(third.moves[0].mv.castling);
+        assert_eq!(third.moves[0].nags, [1]);
+        assert_eq!(third.result, "1/2-1/2");
+    }
+
+    #[test]
+    fn test_annotations_roundtrip() {
+        let pgn = r#"[Event "Analysis"]
+[Result "*"]
+
+1. e4 {[%eval 0.35] best by test} 1... e5 $1 2. Nf3 {[%eval -1.20]} 2... Nc6?! 3. Bb5
+{the Spanish} 3... a6 *
+"#;
+        let game = parse_pgn(pgn).unwrap();
+        assert_eq!(game.moves[0].eval, Some(35));
+        assert_eq!(game.moves[0].comment.as_deref(), Some("best by test"));
+        assert_eq!(game.moves[1].nags, [1]);
+        assert_eq!(game.moves[2].eval, Some(-120));
+        assert_eq!(game.moves[2].comment, None);
+        assert_eq!(game.moves[3].nags, [6]);
+        assert_eq!(game.moves[4].comment.as_deref(), Some("the Spanish"));
+
+        let exported = game.to_pgn().unwrap();
+        assert_eq!(
+            exported,
+            "[Event \"Analysis\"]\n[Result \"*\"]\n\n\
+             1. e4 {[%eval 0.35] best by test} 1... e5 $1 2. Nf3 {[%eval -1.20]} 2... Nc6 $6\n\
+             3. Bb5 {the Spanish} 3... a6 *\n"
+        );
+        let again = parse_pgn(&exported).unwrap();
+        assert_eq!(again.moves, game.moves);
+    }
+
+    #[test]
+    fn test_several_nags_and_escaped_tags() {
+        let pgn = r#"[Event "The \"Immortal\" game"]
+[Site "C:\\games\\"]
+[Result "*"]
+
+1. e4 $1 $14 1... e5 !? $36 *
+"#;
+        let game = parse_pgn(pgn).unwrap();
+        assert_eq!(game.tag("Event"), Some("The \"Immortal\" game"));
+        assert_eq!(game.tag("Site"), Some("C:\\games\\"));
+        assert_eq!(game.moves[0].nags, [1, 14]);
+        assert_eq!(game.moves[1].nags, [5, 36]);
+
+        let exported = game.to_pgn().unwrap();
+        assert!(
+            exported.contains("[Site \"C:\\\\games\\\\\"]"),
+            "{}",
+            exported
+        );
+        assert!(
+            exported.contains("1. e4 $1 $14 e5 $5 $36 *"),
+            "{}",
+            exported
+        );
+        let again = parse_pgn(&exported).unwrap();
+        assert_eq!(again.tags, game.tags);
+        assert_eq!(again.moves, game.moves);
+    }
+
+    #[test]
+    fn test_numbering_from_mid_game_fen() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 3 14";
+        let pgn = format!(
+            "[FEN \"{}\"]\n[Result \"*\"]\n\n14... Nf6 15. Bb5 {{pinning}} 15... a6 16. Bxc6 *\n",
+            fen
+        );
+        let game = parse_pgn(&pgn).unwrap();
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.to_pgn().unwrap(), pgn);
+
+        let mut position = ChessGame::new_position(fen).unwrap();
+        assert_eq!(position.san_move_list(), "");
+        for annotated in &game.moves {
+            position.make_move(annotated.mv).unwrap();
+        }
+        assert_eq!(position.san_move_list(), "14... Nf6 15. Bb5 a6 16. Bxc6");
+    }
+}
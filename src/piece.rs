@@ -1,3 +1,4 @@
+use crate::board::Coord;
 use crate::color::Color;
 use termion::color;
 
@@ -38,7 +39,7 @@ impl Display for Piece {
 }
 
 impl Piece {
-    pub fn get_symbol(&self) -> &str {
+    pub fn get_symbol(&self) -> &'static str {
         match self {
             King(_c) => "♚",
             Queen(_c) => "♛",
@@ -49,7 +50,7 @@ impl Piece {
         }
     }
 
-    pub fn get_unicode(&self) -> &str {
+    pub fn get_unicode(&self) -> &'static str {
         match self {
             King(White) => "♔",
             King(Black) => "♚",
@@ -65,7 +66,7 @@ impl Piece {
             Rook(Black) => "♜",
         }
     }
-    pub fn get_letter(&self) -> &str {
+    pub fn get_letter(&self) -> &'static str {
         match self {
             King(_) => "K",
             Queen(_) => "Q",
@@ -85,6 +86,28 @@ impl Piece {
             Pawn(_) => "pawn",
         }
     }
+    /// Material value in pawns. The king has no material value.
+    pub fn value(&self) -> i32 {
+        match self {
+            King(_) => 0,
+            Queen(_) => 9,
+            Rook(_) => 5,
+            Bishop(_) => 3,
+            Knight(_) => 3,
+            Pawn(_) => 1,
+        }
+    }
+    /// The same kind of piece, with the given color.
+    pub fn with_color(&self, color: Color) -> Piece {
+        match self {
+            King(_) => King(color),
+            Queen(_) => Queen(color),
+            Rook(_) => Rook(color),
+            Bishop(_) => Bishop(color),
+            Knight(_) => Knight(color),
+            Pawn(_) => Pawn(color),
+        }
+    }
     pub fn get_color(&self) -> Color {
         match self {
             King(c) | Queen(c) | Rook(c) | Bishop(c) | Knight(c) | Pawn(c) => *c,
@@ -111,4 +134,58 @@ impl Piece {
     pub fn is_sliding_piece(&self) -> bool {
         matches!(self, Queen(_) | Bishop(_) | Rook(_))
     }
+    /// Returns true if the movement rules of the piece allow going from `from` to `to` on an
+    /// empty board. Pawns can push (twice from their starting rank) or capture diagonally
+    /// forward. Castling is not considered a geometric king move.
+    pub fn can_reach(&self, from: Coord, to: Coord) -> bool {
+        let df = to.file() as i32 - from.file() as i32;
+        let dr = to.rank() as i32 - from.rank() as i32;
+        if df == 0 && dr == 0 {
+            return false;
+        }
+        match self {
+            King(_) => df.abs() <= 1 && dr.abs() <= 1,
+            Queen(_) => df == 0 || dr == 0 || df.abs() == dr.abs(),
+            Rook(_) => df == 0 || dr == 0,
+            Bishop(_) => df.abs() == dr.abs(),
+            Knight(_) => (df.abs() == 1 && dr.abs() == 2) || (df.abs() == 2 && dr.abs() == 1),
+            Pawn(c) => {
+                let (forward, start_rank) = (c.pawn_direction(), c.pawn_start_rank());
+                (dr == forward && df.abs() <= 1)
+                    || (dr == 2 * forward && df == 0 && from.rank() == start_rank)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Coord;
+    use crate::color::Color::*;
+    use crate::piece::Piece::{self, *};
+
+    fn reachable(piece: Piece, from: &str) -> Vec<String> {
+        let from: Coord = from.parse().unwrap();
+        (0..64)
+            .map(Coord::from_tile)
+            .filter(|to| piece.can_reach(from, *to))
+            .map(|c| c.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_can_reach() {
+        assert_eq!(
+            reachable(Knight(White), "d4"),
+            ["c2", "e2", "b3", "f3", "b5", "f5", "c6", "e6"]
+        );
+        assert_eq!(reachable(Knight(Black), "a1"), ["c2", "b3"]);
+        assert_eq!(reachable(King(White), "d4").len(), 8);
+        assert_eq!(reachable(Rook(White), "d4").len(), 14);
+        assert_eq!(reachable(Bishop(White), "d4").len(), 13);
+        assert_eq!(reachable(Queen(White), "d4").len(), 27);
+        assert_eq!(reachable(Pawn(White), "e2"), ["d3", "e3", "f3", "e4"]);
+        assert_eq!(reachable(Pawn(White), "e3"), ["d4", "e4", "f4"]);
+        assert_eq!(reachable(Pawn(Black), "e7"), ["e5", "d6", "e6", "f6"]);
+    }
 }
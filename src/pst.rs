@@ -0,0 +1,148 @@
+// Piece-square tables: a bonus or penalty for each piece depending on the square it stands on.
+//
+// Tables are written as seen from White's side of the board, rank 8 first, so they read like a
+// diagram. Black pieces use the same tables mirrored vertically.
+
+use crate::board::{Board, Coord};
+use crate::color::Color::{self, White};
+use crate::piece::Piece::{self, *};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PieceSquareTables {
+    pub pawn: [i32; 64],
+    pub knight: [i32; 64],
+    pub bishop: [i32; 64],
+    pub rook: [i32; 64],
+    pub queen: [i32; 64],
+    pub king: [i32; 64],
+}
+
+impl PieceSquareTables {
+    pub fn table(&self, piece: Piece) -> &[i32; 64] {
+        match piece {
+            Pawn(_) => &self.pawn,
+            Knight(_) => &self.knight,
+            Bishop(_) => &self.bishop,
+            Rook(_) => &self.rook,
+            Queen(_) => &self.queen,
+            King(_) => &self.king,
+        }
+    }
+
+    /// The table value of a piece on a square, mirrored for Black.
+    pub fn value(&self, piece: Piece, c: Coord) -> i32 {
+        let row = if piece.get_color() == White {
+            7 - c.rank()
+        } else {
+            c.rank()
+        };
+        self.table(piece)[row * 8 + c.file()]
+    }
+}
+
+/// The tables of Tomasz Michniewski's "Simplified Evaluation Function", in centipawns.
+impl Default for PieceSquareTables {
+    #[rustfmt::skip]
+    fn default() -> Self {
+        Self {
+            pawn: [
+                  0,   0,   0,   0,   0,   0,   0,   0,
+                 50,  50,  50,  50,  50,  50,  50,  50,
+                 10,  10,  20,  30,  30,  20,  10,  10,
+                  5,   5,  10,  25,  25,  10,   5,   5,
+                  0,   0,   0,  20,  20,   0,   0,   0,
+                  5,  -5, -10,   0,   0, -10,  -5,   5,
+                  5,  10,  10, -20, -20,  10,  10,   5,
+                  0,   0,   0,   0,   0,   0,   0,   0,
+            ],
+            knight: [
+                -50, -40, -30, -30, -30, -30, -40, -50,
+                -40, -20,   0,   0,   0,   0, -20, -40,
+                -30,   0,  10,  15,  15,  10,   0, -30,
+                -30,   5,  15,  20,  20,  15,   5, -30,
+                -30,   0,  15,  20,  20,  15,   0, -30,
+                -30,   5,  10,  15,  15,  10,   5, -30,
+                -40, -20,   0,   5,   5,   0, -20, -40,
+                -50, -40, -30, -30, -30, -30, -40, -50,
+            ],
+            bishop: [
+                -20, -10, -10, -10, -10, -10, -10, -20,
+                -10,   0,   0,   0,   0,   0,   0, -10,
+                -10,   0,   5,  10,  10,   5,   0, -10,
+                -10,   5,   5,  10,  10,   5,   5, -10,
+                -10,   0,  10,  10,  10,  10,   0, -10,
+                -10,  10,  10,  10,  10,  10,  10, -10,
+                -10,   5,   0,   0,   0,   0,   5, -10,
+                -20, -10, -10, -10, -10, -10, -10, -20,
+            ],
+            rook: [
+                  0,   0,   0,   0,   0,   0,   0,   0,
+                  5,  10,  10,  10,  10,  10,  10,   5,
+                 -5,   0,   0,   0,   0,   0,   0,  -5,
+                 -5,   0,   0,   0,   0,   0,   0,  -5,
+                 -5,   0,   0,   0,   0,   0,   0,  -5,
+                 -5,   0,   0,   0,   0,   0,   0,  -5,
+                 -5,   0,   0,   0,   0,   0,   0,  -5,
+                  0,   0,   0,   5,   5,   0,   0,   0,
+            ],
+            queen: [
+                -20, -10, -10,  -5,  -5, -10, -10, -20,
+                -10,   0,   0,   0,   0,   0,   0, -10,
+                -10,   0,   5,   5,   5,   5,   0, -10,
+                 -5,   0,   5,   5,   5,   5,   0,  -5,
+                  0,   0,   5,   5,   5,   5,   0,  -5,
+                -10,   5,   5,   5,   5,   5,   0, -10,
+                -10,   0,   5,   0,   0,   0,   0, -10,
+                -20, -10, -10,  -5,  -5, -10, -10, -20,
+            ],
+            king: [
+                -30, -40, -40, -50, -50, -40, -40, -30,
+                -30, -40, -40, -50, -50, -40, -40, -30,
+                -30, -40, -40, -50, -50, -40, -40, -30,
+                -30, -40, -40, -50, -50, -40, -40, -30,
+                -20, -30, -30, -40, -40, -30, -30, -20,
+                -10, -20, -20, -20, -20, -20, -20, -10,
+                 20,  20,   0,   0,   0,   0,  20,  20,
+                 20,  30,  10,   0,   0,  10,  30,  20,
+            ],
+        }
+    }
+}
+
+impl Board {
+    /// Sums the table values of all the pieces of `color`.
+    pub fn pst_score(&self, color: Color, tables: &PieceSquareTables) -> i32 {
+        (0..64)
+            .filter_map(|t| self[t].map(|p| (Coord::from_tile(t), p)))
+            .filter(|(_, p)| p.get_color() == color)
+            .map(|(c, p)| tables.value(p, c))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pst::PieceSquareTables;
+    use crate::*;
+
+    #[test]
+    fn test_pst_score() {
+        let tables = PieceSquareTables::default();
+        let game = ChessGame::new();
+        let white = game.board.pst_score(White, &tables);
+        assert_eq!(white, game.board.pst_score(Black, &tables));
+
+        // a knight in the center beats one in the corner, for both colors
+        let centre = ChessGame::new_position("4k3/8/8/4n3/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let corner = ChessGame::new_position("n3k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        for color in [White, Black] {
+            assert!(
+                centre.board.pst_score(color, &tables) > corner.board.pst_score(color, &tables)
+            );
+        }
+        assert_eq!(
+            centre.board.pst_score(White, &tables),
+            centre.board.pst_score(Black, &tables)
+        );
+    }
+}
@@ -0,0 +1,46 @@
+// Random games for tests. Moves are picked with a seeded xorshift generator, so every run plays
+// the same games and a failure can be replayed.
+
+use crate::ChessGame;
+
+pub(crate) struct MovePicker {
+    state: u64,
+}
+
+impl MovePicker {
+    /// The seed must not be zero, or xorshift only ever yields zero.
+    pub(crate) fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0);
+        Self { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Plays up to `plies` random legal moves from `start`, stopping early when the game is
+    /// over. `visit` sees every position along the way, the start and the final one included.
+    /// Returns the game at the end, with all the moves in its history.
+    pub(crate) fn play(
+        &mut self,
+        start: &ChessGame,
+        plies: usize,
+        mut visit: impl FnMut(&ChessGame),
+    ) -> ChessGame {
+        let mut game = start.clone();
+        visit(&game);
+        for _ in 0..plies {
+            let moves = game.legal_moves_sorted();
+            if moves.is_empty() {
+                break;
+            }
+            let m = moves[self.next() as usize % moves.len()];
+            game.make_move(m).unwrap();
+            visit(&game);
+        }
+        game
+    }
+}
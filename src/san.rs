@@ -0,0 +1,367 @@
+// Standard Algebraic Notation (SAN), the move notation used by PGN and most humans.
+
+use anyhow::*;
+
+use crate::board::Coord;
+use crate::moves::Move;
+use crate::piece::Piece::{self, *};
+use crate::ChessGame;
+
+/// Returns true if both pieces are of the same kind, regardless of their color.
+fn same_kind(a: Piece, b: Piece) -> bool {
+    a.get_letter() == b.get_letter()
+}
+
+impl ChessGame {
+    /// Writes a legal move in SAN, with disambiguation and check or mate suffixes.
+    pub fn move_to_san(&self, m: Move) -> String {
+        self.san_with(m, &self.legal_moves())
+    }
+
+    /// Every legal move in SAN: pawn moves first, then piece moves and castling, each in
+    /// alphabetical order.
+    pub fn legal_moves_san(&self) -> Vec<String> {
+        let legal = self.legal_moves();
+        let mut sans: Vec<String> = legal.iter().map(|m| self.san_with(*m, &legal)).collect();
+        sans.sort_by(|a, b| {
+            let is_piece = |s: &String| s.starts_with(|c: char| c.is_ascii_uppercase());
+            (is_piece(a), a).cmp(&(is_piece(b), b))
+        });
+        sans
+    }
+
+    /// `move_to_san` with the legal moves of the position already generated.
+    fn san_with(&self, m: Move, legal: &[Move]) -> String {
+        let mut san = if m.castling {
+            if m.target.file() == 6 {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        } else {
+            let capture = m.enpassant || self.board[m.target].is_some();
+            let mut san = String::new();
+            if let Pawn(_) = m.piece {
+                if capture {
+                    san.push_str(&m.source.to_string()[..1]);
+                }
+            } else {
+                san.push_str(m.piece.get_letter());
+                san.push_str(&san_disambiguation(m, legal));
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&m.target.to_string());
+            if let Some(p) = m.promoted_piece {
+                san.push('=');
+                san.push_str(p.get_letter());
+            }
+            san
+        };
+        let mut after = self.clone();
+        if after.make_move(m).is_ok() && after.is_in_check() {
+            san.push(if after.has_legal_move() { '+' } else { '#' });
+        }
+        san
+    }
+
+    /// Describes a legal move in plain English, like "White knight captures on f6" or "Black
+    /// castles kingside", for screen readers and beginners.
+    pub fn describe_move(&self, m: Move) -> String {
+        let color = m.piece.get_color().name();
+        let mut text = if m.castling {
+            let side = if m.target.file() == 6 {
+                "kingside"
+            } else {
+                "queenside"
+            };
+            format!("{} castles {}", color, side)
+        } else {
+            let piece = format!("{} {}", color, m.piece.get_name());
+            let mut text = if m.enpassant {
+                format!("{} captures en passant on {}", piece, m.target)
+            } else if self.board[m.target].is_some() {
+                format!("{} captures on {}", piece, m.target)
+            } else {
+                format!("{} moves to {}", piece, m.target)
+            };
+            if let Some(p) = m.promoted_piece {
+                text.push_str(&format!(" and promotes to a {}", p.get_name()));
+            }
+            text
+        };
+        let mut after = self.clone();
+        if after.make_move(m).is_ok() && after.is_in_check() {
+            text.push_str(if after.has_legal_move() {
+                ", check"
+            } else {
+                ", checkmate"
+            });
+        }
+        text
+    }
+
+    /// Writes every legal move in SAN and parses it back, returning true if all of them come
+    /// back as the same move. Handy for checking the notation code against position suites.
+    pub fn san_roundtrip_ok(&self) -> bool {
+        self.legal_moves().into_iter().all(|m| {
+            self.parse_san(&self.move_to_san(m))
+                .is_ok_and(|parsed| parsed == m)
+        })
+    }
+
+    /// Reads a move in SAN and returns the matching legal move. Check, mate and annotation
+    /// suffixes are ignored. Fails if no legal move matches or if the notation is ambiguous.
+    pub fn parse_san(&self, san: &str) -> Result<Move, anyhow::Error> {
+        let notation = san.trim_end_matches(['+', '#', '!', '?']);
+        let color = self.side_to_move;
+        let legal = self.legal_moves();
+
+        if notation == "O-O" || notation == "0-0" || notation == "O-O-O" || notation == "0-0-0" {
+            let king_side = notation.len() == 3;
+            return self
+                .castling_move(color, king_side)
+                .ok_or_else(|| anyhow!("illegal castling move {}", san));
+        }
+
+        let mut chars: Vec<char> = notation.chars().collect();
+        let piece = match chars.first() {
+            Some('K') => King(color),
+            Some('Q') => Queen(color),
+            Some('R') => Rook(color),
+            Some('B') => Bishop(color),
+            Some('N') => Knight(color),
+            Some(_) => Pawn(color),
+            None => return Err(anyhow!("empty SAN move")),
+        };
+        if piece != Pawn(color) {
+            chars.remove(0);
+        }
+
+        // promotion, written as e8=Q or e8Q
+        let mut promotion = None;
+        if let Some(last) = chars.last() {
+            if let Some(p) = Piece::new_from_fen_char(*last) {
+                if piece == Pawn(color) && !matches!(p, King(_) | Pawn(_)) {
+                    promotion = Some(p);
+                    chars.pop();
+                    if chars.last() == Some(&'=') {
+                        chars.pop();
+                    }
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(anyhow!("malformed SAN move {}", san));
+        }
+        let target_chars = chars.split_off(chars.len() - 2);
+        let target =
+            parse_square(&target_chars).ok_or_else(|| anyhow!("malformed SAN move {}", san))?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in chars.iter().filter(|c| **c != 'x' && **c != ':') {
+            match c {
+                'a'..='h' => from_file = Some(*c as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(*c as usize - '1' as usize),
+                _ => return Err(anyhow!("malformed SAN move {}", san)),
+            }
+        }
+
+        let promotion_matches = |m: &Move| match (m.promoted_piece, promotion) {
+            (None, None) => true,
+            (Some(a), Some(b)) => same_kind(a, b),
+            _ => false,
+        };
+        let candidates: Vec<Move> = legal
+            .into_iter()
+            .filter(|m| {
+                m.piece == piece
+                    && m.target == target
+                    && !m.castling
+                    && from_file.is_none_or(|f| m.source.file() == f)
+                    && from_rank.is_none_or(|r| m.source.rank() == r)
+                    && promotion_matches(m)
+            })
+            .collect();
+        match candidates.len() {
+            1 => Ok(candidates[0]),
+            0 => Err(anyhow!("illegal move {}", san)),
+            _ => Err(anyhow!("ambiguous move {}", san)),
+        }
+    }
+
+    /// Reads a move in SAN and plays it, returning the move. The game is left untouched if the
+    /// notation is illegal or ambiguous.
+    pub fn play_san(&mut self, san: &str) -> Result<Move, anyhow::Error> {
+        let m = self.parse_san(san)?;
+        self.make_move(m)?;
+        Ok(m)
+    }
+}
+
+/// The source file and/or rank needed to tell `m` apart from other moves in `legal` of the
+/// same kind of piece to the same square.
+fn san_disambiguation(m: Move, legal: &[Move]) -> String {
+    let rivals: Vec<&Move> = legal
+        .iter()
+        .filter(|o| o.target == m.target && o.source != m.source && o.piece == m.piece)
+        .collect();
+    let source = m.source.to_string();
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|o| o.source.file() != m.source.file()) {
+        source[..1].to_string()
+    } else if rivals.iter().all(|o| o.source.rank() != m.source.rank()) {
+        source[1..].to_string()
+    } else {
+        source
+    }
+}
+
+fn parse_square(chars: &[char]) -> Option<Coord> {
+    match chars {
+        [f, r] => Coord::from_file_rank_chars(*f, *r),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_san_roundtrip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+        ] {
+            let game = ChessGame::new_position(fen).unwrap();
+            for m in game.legal_moves() {
+                let san = game.move_to_san(m);
+                assert_eq!(game.parse_san(&san).unwrap(), m, "{}", san);
+            }
+        }
+    }
+
+    #[test]
+    fn test_san_roundtrip_ok() {
+        assert!(ChessGame::new().san_roundtrip_ok());
+        // promotions with captures, and Chess960 castling with the king next to its rook
+        for fen in [
+            "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BN1KR w HFhf - 2 9",
+        ] {
+            assert!(
+                ChessGame::new_position(fen).unwrap().san_roundtrip_ok(),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    fn san_of(game: &ChessGame, uci: &str) -> String {
+        let m = game
+            .legal_moves()
+            .into_iter()
+            .find(|m| m.to_uci() == uci)
+            .unwrap();
+        game.move_to_san(m)
+    }
+
+    #[test]
+    fn test_legal_moves_san() {
+        let sans = ChessGame::new().legal_moves_san();
+        assert_eq!(sans.len(), 20);
+        assert_eq!(sans[..4], ["a3", "a4", "b3", "b4"]);
+        assert_eq!(sans[16..], ["Na3", "Nc3", "Nf3", "Nh3"]);
+
+        // two knights that need disambiguating, and a checking move
+        let game = ChessGame::new_position("3k4/8/8/8/8/8/8/R2NKN1R w - - 0 1").unwrap();
+        let sans = game.legal_moves_san();
+        for san in ["Nde3", "Nfe3", "Ra8+", "Rg1"] {
+            assert!(sans.iter().any(|s| s == san), "{} not in {:?}", san, sans);
+        }
+        assert!(!sans.iter().any(|s| s == "Ne3"));
+    }
+
+    #[test]
+    fn test_san_disambiguation() {
+        // knights on b1 and f1 reach d2, rooks on a1 and a5 reach a3
+        let game = ChessGame::new_position("2k5/8/8/R7/8/8/8/RN3NQK w - - 0 1").unwrap();
+        assert_eq!(san_of(&game, "b1d2"), "Nbd2");
+        assert_eq!(san_of(&game, "a1a3"), "R1a3");
+        assert_eq!(san_of(&game, "b1c3"), "Nc3");
+        assert!(game.parse_san("Nd2").is_err());
+        assert!(game.parse_san("Ke2").is_err());
+        // queens on e4, h4 and h1 all reach e1
+        let game = ChessGame::new_position("2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1").unwrap();
+        assert_eq!(san_of(&game, "h4e1"), "Qh4e1");
+        assert_eq!(game.parse_san("Qh4e1").unwrap().to_uci(), "h4e1");
+    }
+
+    #[test]
+    fn test_describe_move() {
+        let describe = |fen: &str, san: &str| {
+            let game = ChessGame::new_position(fen).unwrap();
+            game.describe_move(game.parse_san(san).unwrap())
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(describe(start, "Nf3"), "White knight moves to f3");
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(describe(kiwipete, "Qxf6"), "White queen captures on f6");
+        assert_eq!(describe(kiwipete, "O-O"), "White castles kingside");
+        let black = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1";
+        assert_eq!(describe(black, "O-O-O"), "Black castles queenside");
+        let ep = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(describe(ep, "exf6"), "White pawn captures en passant on f6");
+        let promotion = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            describe(promotion, "a8=Q"),
+            "White pawn moves to a8 and promotes to a queen"
+        );
+        assert_eq!(
+            describe(promotion, "axb8=N"),
+            "White pawn captures on b8 and promotes to a knight"
+        );
+        let mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        assert_eq!(describe(mate, "Qh4#"), "Black queen moves to h4, checkmate");
+    }
+
+    #[test]
+    fn test_play_san() {
+        let mut game = ChessGame::new();
+        for san in ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6"] {
+            game.play_san(san).unwrap();
+        }
+        let fen = game.to_fen();
+        let error = game.play_san("Qxf8").err().unwrap();
+        assert_eq!(error.to_string(), "illegal move Qxf8");
+        assert!(game.play_san("N3").is_err());
+        assert_eq!(game.to_fen(), fen);
+
+        let mate = game.play_san("Qxf7#").unwrap();
+        assert_eq!(mate.to_uci(), "h5f7");
+        assert!(game.is_checkmate());
+
+        let mut knights = ChessGame::new_position("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        let error = knights.play_san("Nd2").err().unwrap();
+        assert_eq!(error.to_string(), "ambiguous move Nd2");
+    }
+
+    #[test]
+    fn test_san_suffixes() {
+        let game = ChessGame::new_position(
+            "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2",
+        )
+        .unwrap();
+        let mate = game.parse_san("Qh4#").unwrap();
+        assert_eq!(game.move_to_san(mate), "Qh4#");
+        let promotion = ChessGame::new_position("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = promotion.parse_san("e8=N+").unwrap();
+        assert_eq!(promotion.move_to_san(m), "e8=N+");
+        assert_eq!(promotion.parse_san("e8Q").unwrap().to_uci(), "e7e8q");
+    }
+}
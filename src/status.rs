@@ -0,0 +1,338 @@
+// Game termination rules: checkmate, stalemate and the different draw conditions.
+
+use crate::color::Color;
+use crate::movegen::is_attacked;
+use crate::moves::Move;
+use crate::piece::Piece::*;
+use crate::ChessGame;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    /// Checkmate, holding the color of the winner.
+    Checkmate(Color),
+    Stalemate,
+    DrawByRepetition,
+    DrawByFiftyMove,
+    DrawByInsufficientMaterial,
+}
+
+/// A draw a player may claim under FIDE rules, as opposed to one that ends the game by itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoveRule,
+}
+
+impl ChessGame {
+    /// Returns true if the side to move is in check and has no legal moves.
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check() && !self.has_legal_move()
+    }
+
+    /// Returns true if the side to move is not in check but has no legal moves.
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_in_check() && !self.has_legal_move()
+    }
+
+    /// Returns true if play can go on from this position as far as turn order goes: both kings
+    /// are on the board, the side that just moved didn't leave its king in check, and the side
+    /// to move isn't checked by more than two pieces. Mated and stalemated positions are
+    /// playable, play just ends right away. Unlike `fen::validate_fen`, which checks that the
+    /// position is consistent on its own, this checks that it could come up in a game.
+    pub fn is_playable(&self) -> bool {
+        let mover = self.side_to_move.opponent();
+        match (self.find_king(self.side_to_move), self.find_king(mover)) {
+            (Some(_), Some(king)) => {
+                !is_attacked(&self.board, king, self.side_to_move)
+                    && self.check_info().checkers.len() <= 2
+            }
+            _ => false,
+        }
+    }
+
+    /// The legal move of the side to move, if it has exactly one.
+    pub fn only_move(&self) -> Option<Move> {
+        match self.legal_moves()[..] {
+            [m] => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Plays forced moves from this position, as long as the side to move has only one legal
+    /// move, up to `max` plies. Returns the moves played, which may be none.
+    pub fn forced_sequence(&self, max: usize) -> Vec<Move> {
+        let mut game = self.clone();
+        let mut moves = vec![];
+        while moves.len() < max {
+            match game.only_move() {
+                Some(m) => {
+                    // the only legal move can always be made
+                    game.make_move(m).unwrap();
+                    moves.push(m);
+                }
+                None => break,
+            }
+        }
+        moves
+    }
+
+    /// All the legal moves that deliver checkmate.
+    pub fn mate_in_one(&self) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|m| self.with_move(*m).is_ok_and(|g| g.is_checkmate()))
+            .collect()
+    }
+
+    /// Returns true if the side to move can deliver checkmate right away.
+    pub fn is_mate_in_one(&self) -> bool {
+        self.legal_moves()
+            .into_iter()
+            .any(|m| self.with_move(m).is_ok_and(|g| g.is_checkmate()))
+    }
+
+    /// Returns true if the side to move has a move that threatens mate: a move that doesn't give
+    /// check, after which it would have a mate in one were the opponent to pass. Checks are left
+    /// out because passing out of check isn't legal.
+    pub fn has_mate_threat(&self) -> bool {
+        self.legal_moves().into_iter().any(|m| {
+            self.with_move(m)
+                .is_ok_and(|g| !g.is_in_check() && g.with_null_move().is_mate_in_one())
+        })
+    }
+
+    /// The same position with the other side to move, as if the side to move had passed.
+    fn with_null_move(&self) -> ChessGame {
+        let mut passed = self.clone();
+        passed.side_to_move = self.side_to_move.opponent();
+        // a pawn that double pushed can't be taken en passant after a pass
+        passed.enpassant_target_square = None;
+        passed.refresh_hash();
+        passed
+    }
+
+    /// Returns true if 50 moves (100 plies) have passed without captures or pawn moves.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// Returns true if the current position has appeared at least three times in the game.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// Returns true if neither side has enough material to ever deliver checkmate: king against
+    /// king, king and a minor piece against king, or kings and bishops all on the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut bishop_square_colors = vec![];
+        for t in 0..64 {
+            match self.board[t] {
+                Some(Pawn(_)) | Some(Rook(_)) | Some(Queen(_)) => return false,
+                Some(Knight(_)) => knights += 1,
+                Some(Bishop(_)) => bishop_square_colors.push((t / 8 + t % 8) % 2),
+                _ => (),
+            }
+        }
+        match (knights, bishop_square_colors.len()) {
+            (0, 0) | (1, 0) => true,
+            (0, _) => bishop_square_colors
+                .iter()
+                .all(|c| *c == bishop_square_colors[0]),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the game is drawn without anyone claiming it: stalemate, insufficient
+    /// material, the same position five times, or 75 moves (150 plies) without captures or pawn
+    /// moves unless the last of them mated.
+    pub fn is_automatic_draw(&self) -> bool {
+        self.is_stalemate()
+            || self.is_insufficient_material()
+            || self.repetition_count() >= 5
+            || (self.halfmove_clock >= 150 && !self.is_checkmate())
+    }
+
+    /// The draw the side to move may claim in the current position, if any. Repetition is
+    /// reported before the fifty-move rule when both apply. There's nothing to claim once the
+    /// game is over, by mate or by an automatic draw.
+    pub fn can_claim_draw(&self) -> Option<DrawClaim> {
+        if !self.has_legal_move() || self.is_automatic_draw() {
+            None
+        } else if self.is_threefold_repetition() {
+            Some(DrawClaim::ThreefoldRepetition)
+        } else if self.is_fifty_move_draw() {
+            Some(DrawClaim::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the state of the game. Checkmate and stalemate are checked before the draw rules,
+    /// so a mate delivered on the 100th ply is still a mate. Repetition and fifty-move draws are
+    /// reported as soon as they can be claimed; use `can_claim_draw` and `is_automatic_draw` to
+    /// tell the two apart.
+    pub fn status(&self) -> GameStatus {
+        if !self.has_legal_move() {
+            if self.is_in_check() {
+                return GameStatus::Checkmate(self.side_to_move.opponent());
+            }
+            return GameStatus::Stalemate;
+        }
+        if self.is_insufficient_material() {
+            GameStatus::DrawByInsufficientMaterial
+        } else if self.is_threefold_repetition() {
+            GameStatus::DrawByRepetition
+        } else if self.is_fifty_move_draw() {
+            GameStatus::DrawByFiftyMove
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::Move;
+    use crate::status::{DrawClaim, GameStatus};
+    use crate::*;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(ChessGame::new().status(), GameStatus::Ongoing);
+        let mate = ChessGame::new_position(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        )
+        .unwrap();
+        assert_eq!(mate.status(), GameStatus::Checkmate(Black));
+        let stalemate = ChessGame::new_position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemate.status(), GameStatus::Stalemate);
+        let bare_kings = ChessGame::new_position("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert_eq!(bare_kings.status(), GameStatus::DrawByInsufficientMaterial);
+        let fifty = ChessGame::new_position("8/8/4k3/8/8/3K4/8/R7 w - - 100 80").unwrap();
+        assert_eq!(fifty.status(), GameStatus::DrawByFiftyMove);
+    }
+
+    #[test]
+    fn test_draw_claims() {
+        let mut game = ChessGame::new();
+        assert_eq!(game.can_claim_draw(), None);
+        for _ in 0..2 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                game.play_san(san).unwrap();
+            }
+        }
+        // the start position is on the board for the third time
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::ThreefoldRepetition));
+        assert!(!game.is_automatic_draw());
+        for _ in 0..2 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                game.play_san(san).unwrap();
+            }
+        }
+        assert!(game.is_automatic_draw());
+        assert_eq!(game.can_claim_draw(), None);
+
+        let fifty = ChessGame::new_position("8/8/4k3/8/8/3K4/8/R7 w - - 100 80").unwrap();
+        assert_eq!(fifty.can_claim_draw(), Some(DrawClaim::FiftyMoveRule));
+        assert!(!fifty.is_automatic_draw());
+        let seventy_five = ChessGame::new_position("8/8/4k3/8/8/3K4/8/R7 w - - 150 105").unwrap();
+        assert!(seventy_five.is_automatic_draw());
+        let stalemate = ChessGame::new_position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_automatic_draw());
+        assert_eq!(stalemate.can_claim_draw(), None);
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        let scholar = ChessGame::new_position(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        )
+        .unwrap();
+        let mates: Vec<String> = scholar.mate_in_one().iter().map(|m| m.to_uci()).collect();
+        assert_eq!(mates, ["h5f7"]);
+        let back_rank = ChessGame::new_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(back_rank.is_mate_in_one());
+        assert_eq!(back_rank.mate_in_one()[0].to_uci(), "a1a8");
+        // Ra8+ is check, but the king walks away
+        let check_only = ChessGame::new_position("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(!check_only.is_mate_in_one());
+        assert!(check_only.mate_in_one().is_empty());
+    }
+
+    #[test]
+    fn test_mate_threat() {
+        // any knight move uncovers the rook, threatening Re8 against the back rank
+        let threat = ChessGame::new_position("6k1/5ppp/8/8/8/8/4N3/4R1K1 w - - 0 1").unwrap();
+        assert!(threat.has_mate_threat());
+        assert!(!threat.is_mate_in_one());
+        // with luft on h6 there's no threat
+        let luft = ChessGame::new_position("6k1/5pp1/7p/8/8/8/4N3/4R1K1 w - - 0 1").unwrap();
+        assert!(!luft.has_mate_threat());
+        // the threat belongs to White, not to Black who is to move here
+        let black = ChessGame::new_position("6k1/5ppp/8/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert!(!black.has_mate_threat());
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = ChessGame::new();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for _ in 0..2 {
+            assert_eq!(game.status(), GameStatus::Ongoing);
+            for m in shuffle {
+                let source: Coord = m[0..2].parse().unwrap();
+                let target: Coord = m[2..4].parse().unwrap();
+                let piece = game.board[source].unwrap();
+                game.make_move(Move::new(source, target, piece, None))
+                    .unwrap();
+            }
+        }
+        assert_eq!(game.status(), GameStatus::DrawByRepetition);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let same_color_bishops =
+            ChessGame::new_position("8/8/4k3/2b5/8/3K4/3B4/8 w - - 0 1").unwrap();
+        assert!(same_color_bishops.is_insufficient_material());
+        let opposite_bishops =
+            ChessGame::new_position("8/8/4k3/3b4/8/3K4/3B4/8 w - - 0 1").unwrap();
+        assert!(!opposite_bishops.is_insufficient_material());
+        let knight = ChessGame::new_position("8/8/4k3/8/8/3K4/3N4/8 w - - 0 1").unwrap();
+        assert!(knight.is_insufficient_material());
+    }
+
+    #[test]
+    fn test_is_playable() {
+        assert!(ChessGame::new().is_playable());
+        // White to move while Black's king is already in check from the rook
+        let game = ChessGame::new_position("4k3/8/8/8/8/8/8/K3R3 w - - 0 1").unwrap();
+        assert!(!game.is_playable());
+        assert!(fen::validate_fen("4k3/8/8/8/8/8/8/K3R3 w - - 0 1").is_ok());
+        // checkmate is a finished game, but a playable position
+        let mate = ChessGame::new_position(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        )
+        .unwrap();
+        assert!(mate.is_playable());
+        assert!(!ChessGame::new_position("8/8/8/8/8/8/8/K7 w - - 0 1")
+            .unwrap()
+            .is_playable());
+    }
+
+    #[test]
+    fn test_forced_moves() {
+        assert_eq!(ChessGame::new().only_move(), None);
+        // the black king can only go to b8, after which White has plenty of choice
+        let game = ChessGame::new_position("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+        let m = game.only_move().unwrap();
+        assert_eq!(m.to_uci(), "a8b8");
+        assert_eq!(game.forced_sequence(5), [m]);
+        assert!(game.forced_sequence(0).is_empty());
+        // the white king takes b7 and b8 away
+        let game = ChessGame::new_position("k7/2K5/8/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.only_move().unwrap().to_uci(), "a8a7");
+    }
+}
@@ -0,0 +1,157 @@
+// The Universal Chess Interface (UCI), the protocol between chess GUIs and engines.
+//
+// Moves are written in long algebraic notation (`e2e4`, `e7e8q`), and the GUI sends the position
+// to analyze as a start position or FEN followed by the moves played from it.
+
+use anyhow::*;
+
+use crate::board::Coord;
+use crate::moves::Move;
+use crate::piece::Piece::{self, *};
+use crate::ChessGame;
+
+impl ChessGame {
+    /// Reads a move in UCI notation, like `e2e4` or `e7e8q`, and returns the matching legal
+    /// move. Castling is accepted both as the king's move to the g or c file and as the king
+    /// taking its own rook, as Chess960 GUIs send it.
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, anyhow::Error> {
+        let chars: Vec<char> = uci.chars().collect();
+        let (from, to, promotion) = match chars.as_slice() {
+            [f1, r1, f2, r2, rest @ ..] if rest.len() <= 1 => (
+                Coord::from_file_rank_chars(*f1, *r1),
+                Coord::from_file_rank_chars(*f2, *r2),
+                rest.first(),
+            ),
+            _ => return Err(anyhow!("malformed UCI move {}", uci)),
+        };
+        let (from, to) = from
+            .zip(to)
+            .ok_or_else(|| anyhow!("malformed UCI move {}", uci))?;
+        let promotion = match promotion {
+            Some(c) => Some(
+                Piece::new_from_fen_char(*c)
+                    .filter(|p| !matches!(p, King(_) | Pawn(_)))
+                    .ok_or_else(|| anyhow!("invalid promotion piece in {}", uci))?,
+            ),
+            None => None,
+        };
+
+        let color = self.side_to_move;
+        if self.board[from] == Some(King(color)) && self.board[to] == Some(Rook(color)) {
+            // only the rook castling is named, not any rook the king passes on its way
+            return self
+                .castling_rights
+                .side_of_rook_file(to.file())
+                .filter(|_| to.rank() == color.back_rank())
+                .and_then(|king_side| self.castling_move(color, king_side))
+                .ok_or_else(|| anyhow!("illegal castling move {}", uci));
+        }
+        self.move_between(from, to, promotion)
+    }
+
+    /// Sets up the game from a UCI `position` command, like `position startpos moves e2e4 e7e5`
+    /// or `position fen <FEN> moves ...`. Fails on the first move that isn't legal, giving its
+    /// index, and leaves the game untouched on any error.
+    pub fn apply_uci_position(&mut self, command: &str) -> Result<(), anyhow::Error> {
+        let mut tokens = command.split_whitespace();
+        if tokens.next() != Some("position") {
+            return Err(anyhow!("not a position command: {}", command));
+        }
+        let mut game = self.clone();
+        match tokens.next() {
+            Some("startpos") => {
+                game.set_start_position();
+                match tokens.next() {
+                    Some("moves") | None => {}
+                    Some(other) => return Err(anyhow!("unexpected {} in {}", other, command)),
+                }
+            }
+            // the FEN runs until the "moves" keyword, which is consumed with it
+            Some("fen") => {
+                let fen: Vec<&str> = tokens.by_ref().take_while(|t| *t != "moves").collect();
+                game.apply_fen(&fen.join(" "))?;
+            }
+            _ => return Err(anyhow!("expected startpos or fen in {}", command)),
+        }
+        for (i, uci) in tokens.enumerate() {
+            game.play_uci(i, uci)?;
+        }
+        *self = game;
+        Ok(())
+    }
+
+    fn play_uci(&mut self, index: usize, uci: &str) -> Result<(), anyhow::Error> {
+        let m = self
+            .parse_uci_move(uci)
+            .map_err(|_| anyhow!("move {} ({}) is not legal", index, uci))?;
+        self.make_move(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_parse_uci_move() {
+        let game = ChessGame::new();
+        assert_eq!(game.parse_uci_move("g1f3").unwrap().to_uci(), "g1f3");
+        assert!(game.parse_uci_move("e2e5").is_err());
+        assert!(game.parse_uci_move("e2").is_err());
+        let promotion = ChessGame::new_position("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(promotion.parse_uci_move("a7b8n").unwrap().to_uci(), "a7b8n");
+        assert!(promotion.parse_uci_move("a7a8").is_err());
+        assert!(promotion.parse_uci_move("a7a8k").is_err());
+        // castling as king to g1 or as king takes rook
+        let castling = ChessGame::new_position("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(castling.parse_uci_move("e1g1").unwrap().castling);
+        assert!(castling.parse_uci_move("e1h1").unwrap().castling);
+        assert_eq!(castling.parse_uci_move("e1h1").unwrap().to_uci(), "e1g1");
+        // in Chess960 the king may already stand on g1, so it is written as taking the rook
+        let chess960 = ChessGame::new_position("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+        let ucis: Vec<String> = chess960
+            .legal_moves()
+            .iter()
+            .filter(|m| m.castling)
+            .map(|m| m.to_uci())
+            .collect();
+        assert_eq!(ucis, ["g1h1", "g1b1"]);
+        for uci in ucis {
+            assert_eq!(chess960.parse_uci_move(&uci).unwrap().to_uci(), uci);
+        }
+        // the king castles with the g1 rook, so taking the h1 rook isn't castling
+        let two_rooks = ChessGame::new_position("4k3/8/8/8/8/8/8/5KRR w G - 0 1").unwrap();
+        assert_eq!(two_rooks.parse_uci_move("f1g1").unwrap().to_uci(), "f1g1");
+        assert!(two_rooks.parse_uci_move("f1h1").is_err());
+    }
+
+    #[test]
+    fn test_apply_uci_position() {
+        let mut game = ChessGame::new();
+        game.apply_uci_position("position startpos moves e2e4 e7e5 g1f3")
+            .unwrap();
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(game.history.len(), 3);
+
+        game.apply_uci_position("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1 e8d7")
+            .unwrap();
+        assert_eq!(game.to_fen(), "8/3k4/8/8/8/8/8/5RK1 w - - 2 2");
+        game.apply_uci_position("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1")
+            .unwrap();
+        assert!(game.history.is_empty());
+        game.apply_uci_position("position startpos").unwrap();
+        assert_eq!(game.to_fen(), ChessGame::new().to_fen());
+
+        let error = game
+            .apply_uci_position("position startpos moves e2e4 e2e4")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("move 1 (e2e4)"), "{}", error);
+        // the game is left as it was
+        assert_eq!(game.to_fen(), ChessGame::new().to_fen());
+        assert!(game.apply_uci_position("go wtime 1000").is_err());
+    }
+}
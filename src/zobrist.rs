@@ -0,0 +1,189 @@
+// Zobrist hashing of a game position.
+//
+// The keys are generated at compile time from a fixed seed, so hashes are stable between runs and
+// builds. The hash covers the piece placement, the side to move, castling rights and the en
+// passant file (only when an en passant capture is actually available), which is exactly what
+// makes two positions the same under the repetition rules.
+
+use crate::board::Coord;
+use crate::castling::CastlingRights;
+use crate::color::Color::Black;
+use crate::piece::Piece::{self, *};
+use crate::ChessGame;
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    (state, z ^ (z >> 31))
+}
+
+// 12 pieces * 64 squares, side to move, 4 castling rights, 8 en passant files
+const KEY_COUNT: usize = 12 * 64 + 1 + 4 + 8;
+const SIDE_KEY: usize = 12 * 64;
+const CASTLING_KEYS: usize = SIDE_KEY + 1;
+const ENPASSANT_KEYS: usize = CASTLING_KEYS + 4;
+
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = 0x0c4e551e;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+fn piece_index(p: Piece) -> usize {
+    let kind = match p {
+        King(_) => 0,
+        Queen(_) => 1,
+        Rook(_) => 2,
+        Bishop(_) => 3,
+        Knight(_) => 4,
+        Pawn(_) => 5,
+    };
+    kind + 6 * p.get_color() as usize
+}
+
+/// The key of a piece standing on a square.
+pub fn piece_key(c: Coord, p: Piece) -> u64 {
+    KEYS[piece_index(p) * 64 + c.to_usize()]
+}
+
+/// The identity of a position for repetition purposes: piece placement, side to move, castling
+/// rights and the en passant file when a capture is available. The move clocks are left out, so
+/// transpositions reached at different move numbers get the same key. It's the Zobrist hash of the
+/// position, so two different positions can collide, though it's very unlikely.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PositionKey(pub u64);
+
+/// The key of Black being the side to move.
+pub fn side_key() -> u64 {
+    KEYS[SIDE_KEY]
+}
+
+/// The combined key of a set of castling rights.
+pub fn castling_key(rights: &CastlingRights) -> u64 {
+    let rights = [
+        rights.white_king_side,
+        rights.white_queen_side,
+        rights.black_king_side,
+        rights.black_queen_side,
+    ];
+    let mut key = 0;
+    for (i, right) in rights.iter().enumerate() {
+        if *right {
+            key ^= KEYS[CASTLING_KEYS + i];
+        }
+    }
+    key
+}
+
+impl ChessGame {
+    pub fn position_key(&self) -> PositionKey {
+        PositionKey(self.zobrist_hash())
+    }
+
+    /// Computes the Zobrist hash of the current position from scratch.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for t in 0..64 {
+            if let Some(p) = self.board[t] {
+                hash ^= piece_key(Coord::from_tile(t), p);
+            }
+        }
+        if self.side_to_move == Black {
+            hash ^= side_key();
+        }
+        hash ^ castling_key(&self.castling_rights) ^ self.enpassant_key()
+    }
+
+    /// The key of the en passant file, or 0 if no en passant capture is available.
+    pub(crate) fn enpassant_key(&self) -> u64 {
+        match self.enpassant_target_square {
+            Some(ep) if self.is_enpassant_available() => KEYS[ENPASSANT_KEYS + ep.file()],
+            _ => 0,
+        }
+    }
+
+    /// The Zobrist hash of the current position, kept up to date by `make_move` and
+    /// `unmake_move` instead of being computed from scratch. It matches `zobrist_hash` as long
+    /// as the public fields aren't edited by hand; call `refresh_hash` after doing so.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recomputes the running hash from scratch.
+    pub fn refresh_hash(&mut self) {
+        self.hash = self.zobrist_hash();
+    }
+
+    /// Toggles a piece on a square in the running hash, without touching the board.
+    pub fn xor_piece(&mut self, c: Coord, p: Piece) {
+        self.hash ^= piece_key(c, p);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_transpositions_hash_equal() {
+        let a =
+            ChessGame::new_position("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1")
+                .unwrap();
+        let b =
+            ChessGame::new_position("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 5 3")
+                .unwrap();
+        assert_eq!(a.zobrist_hash(), b.zobrist_hash());
+        assert_ne!(a.zobrist_hash(), ChessGame::new().zobrist_hash());
+        let mut white = a.clone();
+        white.side_to_move = White;
+        assert_ne!(a.zobrist_hash(), white.zobrist_hash());
+        assert_eq!(a.position_key(), b.position_key());
+        assert_eq!(a.hash(), a.zobrist_hash());
+        assert_ne!(a.position_key(), white.position_key());
+    }
+
+    #[test]
+    fn test_incremental_hash() {
+        let default = ChessGame::default();
+        assert_eq!(default.hash(), default.zobrist_hash());
+        let mut cleared = ChessGame::new();
+        cleared.clear();
+        assert_eq!(cleared.hash(), cleared.zobrist_hash());
+        assert_eq!(cleared.repetition_count(), 1);
+        let mut picker = crate::random_games::MovePicker::new(0x9e3779b97f4a7c15);
+        for start in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ]
+        .map(|fen| ChessGame::new_position(fen).unwrap())
+        .into_iter()
+        .chain([
+            ChessGame::new_chess960(0).unwrap(),
+            ChessGame::new_chess960(959).unwrap(),
+        ]) {
+            for _ in 0..200 {
+                let mut game = picker.play(&start, 40, |g| {
+                    assert_eq!(g.hash(), g.zobrist_hash(), "{}", g.to_fen());
+                    // random games reach positions the perft trees don't
+                    crate::perft::assert_generators_agree(g);
+                });
+                while game.unmake_move().is_some() {
+                    assert_eq!(game.hash(), game.zobrist_hash(), "{}", game.to_fen());
+                }
+            }
+        }
+    }
+}